@@ -0,0 +1,85 @@
+use crate::{AIMeta, InputId};
+use bevy::prelude::{Component, Entity, World};
+use std::any::TypeId;
+use std::sync::Arc;
+
+/// The context an action is chosen in, passed to the decision's ActionFactory to build the
+/// action component.
+pub struct ActionContext<'w> {
+    /// The entity that chose the action.
+    pub subject: Entity,
+    pub target: Option<Entity>,
+    /// All of the targets of a multi targeted decision, the first being the target.
+    pub targets: &'w [Entity],
+    /// The score of the decision that chose the action.
+    pub score: f32,
+    /// The world, for reading the components and resources of the subject and target.
+    pub world: &'w World,
+}
+
+impl ActionContext<'_> {
+    /// Returns the subject's raw score for the input, for targeted inputs this is the score of
+    /// the target and for multi targeted inputs the score of the set of targets.
+    pub fn input_score(&self, input: InputId) -> Option<f32> {
+        let ai_meta = self.world.get::<AIMeta>(self.subject)?;
+        if self.targets.len() > 1 {
+            return ai_meta
+                .multi_targeted_input_scores
+                .get(&input)
+                .and_then(|score_map| score_map.get(self.targets))
+                .or_else(|| ai_meta.input_scores.get(&input))
+                .copied();
+        }
+        match self.target {
+            Some(target) => ai_meta
+                .targeted_input_scores
+                .get(&input)
+                .and_then(|score_map| score_map.get(&target))
+                .or_else(|| ai_meta.input_scores.get(&input))
+                .copied(),
+            None => ai_meta.input_scores.get(&input).copied(),
+        }
+    }
+}
+
+type BuildAction = dyn Fn(&mut World, Entity, &[Entity], f32) + Send + Sync;
+
+/// Builds the action component of a decision from the context it was chosen in, rather than
+/// with its Default implementation, see DecisionSettings::with_action_factory.
+#[derive(Clone)]
+pub struct ActionFactory {
+    pub(crate) action: TypeId,
+    build: Arc<BuildAction>,
+}
+
+impl ActionFactory {
+    pub fn new<C: Component>(
+        factory: impl Fn(&ActionContext) -> C + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            action: TypeId::of::<C>(),
+            build: Arc::new(move |world, subject, targets, score| {
+                let action = factory(&ActionContext {
+                    subject,
+                    target: targets.first().copied(),
+                    targets,
+                    score,
+                    world,
+                });
+                world.entity_mut(subject).insert(action);
+            }),
+        }
+    }
+
+    /// Builds the action and inserts it on the subject, replacing any existing action of the
+    /// same type.
+    pub(crate) fn insert(
+        &self,
+        world: &mut World,
+        subject: Entity,
+        targets: &[Entity],
+        score: f32,
+    ) {
+        (self.build)(world, subject, targets, score)
+    }
+}
@@ -1,17 +1,96 @@
+use crate::InputId;
 use bevy::prelude::{Component, Entity};
 use bevy::utils::HashMap;
 use std::any::TypeId;
+use std::time::Duration;
 
 /// A Component which stores all the required state to run the AI Systems.
 #[derive(Component, Clone)]
 pub struct AIMeta {
     pub ai_definition: TypeId,
-    pub input_scores: HashMap<usize, f32>,
-    pub targeted_input_scores: HashMap<usize, HashMap<Entity, f32>>,
+    pub input_scores: HashMap<InputId, f32>,
+    pub targeted_input_scores: HashMap<InputId, HashMap<Entity, f32>>,
+    /// The scores of each combination of targets, see Consideration::multi_targeted.
+    pub multi_targeted_input_scores: HashMap<InputId, HashMap<Vec<Entity>, f32>>,
     pub current_action: Option<TypeId>,
+    /// The index of the decision that chose the current action in the AIDefinition's
+    /// decisions, as several decisions can share an action.
+    pub current_decision: Option<usize>,
     pub current_action_score: f32,
     pub current_action_name: String,
     pub current_target: Option<Entity>,
+    /// All of the current action's targets, for multi targeted actions current_target is the
+    /// first of these.
+    pub current_targets: Vec<Entity>,
+    /// The number of times the entity has been evaluated since the current action was chosen.
+    pub current_action_ticks: u32,
+    /// The elapsed Time at which the current action was chosen.
+    pub current_action_started: Duration,
+    /// The number of times the entity has been evaluated, see DefineAI::set_decision_interval.
+    pub evaluations: u64,
+    /// The action and target pairs that failed recently, see ActionStatus.
+    pub action_failures: Vec<ActionFailure>,
+    /// Whether the entity's inputs and decisions are evaluated on the current tick, see
+    /// DefineAI::set_decision_interval.
+    pub is_due: bool,
+    /// The state of cached input scores, see Consideration::with_cache.
+    pub input_cache: HashMap<InputId, CachedInput>,
+    pub targeted_input_cache: HashMap<InputId, HashMap<Entity, CachedInput>>,
+    /// The targets chosen by each target selector, see Consideration::target_selector.
+    pub selected_targets: HashMap<InputId, Vec<Entity>>,
+}
+
+/// An action and target pair that failed, its score is penalised until the AI's
+/// FailurePenalty expires.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionFailure {
+    pub action: TypeId,
+    pub target: Option<Entity>,
+    /// All of the targets of a multi targeted action, the first being the target.
+    pub targets: Vec<Entity>,
+    /// The AIMeta::evaluations at which the action failed.
+    pub evaluation: u64,
+    /// The elapsed Time at which the action failed.
+    pub time: Duration,
+}
+
+/// Tracks when a cached input score was calculated, the score is reused until one of the
+/// components it was calculated from changes or it has been reused `max_age` times.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CachedInput {
+    /// A fingerprint of the change ticks of the components the score was calculated from.
+    pub change_ticks: u64,
+    /// The number of times the score has been reused.
+    pub age: u32,
+}
+
+impl CachedInput {
+    pub fn new(change_ticks: u64) -> Self {
+        Self {
+            change_ticks,
+            age: 0,
+        }
+    }
+
+    /// Combines the change ticks of the queried components, as returned by
+    /// DetectChanges::last_changed, into a single value.
+    pub fn fingerprint(change_ticks: impl IntoIterator<Item = u32>) -> u64 {
+        change_ticks
+            .into_iter()
+            .fold(0xcbf29ce484222325, |fingerprint, tick| {
+                (fingerprint ^ tick as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// Returns true and ages the score if it can be reused, that is if none of the
+    /// components have changed and it is younger than the max age.
+    pub fn reuse(&mut self, change_ticks: u64, max_age: u32) -> bool {
+        if self.change_ticks != change_ticks || self.age >= max_age {
+            return false;
+        }
+        self.age += 1;
+        true
+    }
 }
 
 impl AIMeta {
@@ -20,10 +99,43 @@ impl AIMeta {
             ai_definition: TypeId::of::<T>(),
             input_scores: HashMap::default(),
             targeted_input_scores: HashMap::default(),
+            multi_targeted_input_scores: HashMap::default(),
             current_action_score: -1.0,
             current_action: None,
+            current_decision: None,
             current_action_name: String::default(),
             current_target: None,
+            current_targets: Vec::new(),
+            current_action_ticks: 0,
+            current_action_started: Duration::ZERO,
+            evaluations: 0,
+            action_failures: Vec::new(),
+            is_due: true,
+            input_cache: HashMap::default(),
+            targeted_input_cache: HashMap::default(),
+            selected_targets: HashMap::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::ai_meta::CachedInput;
+
+    #[test]
+    fn cached_input_is_reused_until_changed_or_too_old() {
+        let change_ticks = CachedInput::fingerprint([3, 7]);
+        let mut cached = CachedInput::new(change_ticks);
+
+        assert!(cached.reuse(change_ticks, 2));
+        assert!(cached.reuse(change_ticks, 2));
+        assert!(!cached.reuse(change_ticks, 2));
+
+        let mut cached = CachedInput::new(change_ticks);
+        assert!(!cached.reuse(CachedInput::fingerprint([3, 8]), 2));
+        assert_ne!(
+            CachedInput::fingerprint([3, 7]),
+            CachedInput::fingerprint([7, 3])
+        );
+    }
+}
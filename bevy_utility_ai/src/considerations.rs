@@ -1,75 +1,322 @@
+use crate::input_id::{filter_input_name, input_name_of, InputMarker};
 use crate::response_curves::{LinearCurve, ResponseCurve};
+use crate::spatial::SpatialFilter;
 use crate::systems::inclusive_filter_input;
-use crate::{AIDefinitions, AITargetEntitySets};
+use crate::InputId;
 use bevy::app::{IntoSystemAppConfig, SystemAppConfig};
-use bevy::ecs::query::WorldQuery;
-use bevy::prelude::{Component, Query, Res};
+use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
 use std::any::type_name;
-
-fn type_name_of<T>(_: T) -> &'static str {
-    type_name::<T>()
-}
+use std::sync::Arc;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum ConsiderationType {
     Simple,
     Targeted,
-    TargetedFilter,
+    TargetedFilter(TargetFilterType),
+    TargetSelector,
+    MultiTargeted,
+}
+
+/// How a targeted filter consideration restricts the targets of a decision, see TargetFilter.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum TargetFilterType {
+    /// Targets must have at least one of the Include filter components.
+    #[default]
+    Include,
+    /// Targets must have all of the Require filter components.
+    Require,
+    /// Targets must not have any of the Exclude filter components.
+    Exclude,
+}
+
+/// Maps a raw input onto 0..1 before it is passed to the response curve, see
+/// Consideration::with_input_range.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum InputRange {
+    Linear { min: f32, max: f32 },
+    Log { min: f32, max: f32 },
+}
+
+impl InputRange {
+    pub fn normalise(&self, input: f32) -> f32 {
+        let normalised = match *self {
+            InputRange::Linear { min, max } => (input - min) / (max - min),
+            InputRange::Log { min, max } => {
+                (input.max(min).ln() - min.ln()) / (max.ln() - min.ln())
+            }
+        };
+        normalised.clamp(0.0, 1.0)
+    }
+
+    /// Returns an error if the range can't normalise inputs, a linear range must have a max
+    /// greater than its min and a log range must also have a positive min.
+    pub fn validate(&self) -> Result<(), String> {
+        let (min, max) = match *self {
+            InputRange::Linear { min, max } => (min, max),
+            InputRange::Log { min, max } => {
+                if min <= 0.0 {
+                    return Err("A log input range must have a positive min".into());
+                }
+                (min, max)
+            }
+        };
+        if !min.is_finite() || !max.is_finite() {
+            return Err("An input range must have a finite min and max".into());
+        }
+        if max <= min {
+            return Err("An input range must have a max greater than its min".into());
+        }
+        Ok(())
+    }
 }
 
 pub struct Consideration {
     pub input_name: String,
-    pub input: usize,
-    pub response_curve: Box<dyn ResponseCurve>,
+    pub input: InputId,
+    pub response_curve: Arc<dyn ResponseCurve>,
+    pub input_range: Option<InputRange>,
     pub consideration_type: ConsiderationType,
+    /// Restricts targeted considerations to nearby targets, see with_max_distance.
+    pub spatial_filter: Option<SpatialFilter>,
+    /// The max age of cached scores, see with_cache.
+    pub cache_max_age: Option<u32>,
+    /// The position of the target of multi targeted considerations a filter applies to, see
+    /// for_target.
+    pub target_slot: Option<usize>,
+    /// The name the InputId was derived from, unlike input_name it can't be changed.
+    pub(crate) id_name: String,
+    /// The path of the input's system, used to detect inputs whose InputIds collide.
+    pub(crate) system_name: &'static str,
     pub(crate) system_app_config: Option<SystemAppConfig>,
 }
 
+impl Clone for Consideration {
+    /// Clones the consideration without its input system, which is only added to the App once.
+    fn clone(&self) -> Self {
+        Self {
+            input_name: self.input_name.clone(),
+            input: self.input,
+            response_curve: self.response_curve.clone(),
+            input_range: self.input_range,
+            consideration_type: self.consideration_type,
+            spatial_filter: self.spatial_filter,
+            cache_max_age: self.cache_max_age,
+            target_slot: self.target_slot,
+            id_name: self.id_name.clone(),
+            system_name: self.system_name,
+            system_app_config: None,
+        }
+    }
+}
+
 impl Consideration {
-    pub fn simple<Q: WorldQuery + 'static>(input: fn(Query<Q>, Res<AIDefinitions>)) -> Self {
+    /// Creates a consideration for a system generated by the input_system macro.
+    pub fn simple<M: InputMarker>(input: impl IntoSystemAppConfig<M> + Copy) -> Self {
+        Self::input_system(input, ConsiderationType::Simple)
+    }
+
+    /// Creates a consideration for a system generated by the targeted_input_system macro.
+    pub fn targeted<M: InputMarker>(input: impl IntoSystemAppConfig<M> + Copy) -> Self {
+        Self::input_system(input, ConsiderationType::Targeted)
+    }
+
+    /// Creates a consideration for a system generated by the multi_targeted_input_system macro,
+    /// the decision is scored for each combination of targets. Decisions can't mix targeted and
+    /// multi targeted considerations, and target selectors aren't supported. The number of
+    /// combinations grows with the power of the number of targets, so restrict each target with
+    /// filters, see for_target.
+    pub fn multi_targeted<M: InputMarker>(input: impl IntoSystemAppConfig<M> + Copy) -> Self {
+        Self::input_system(input, ConsiderationType::MultiTargeted)
+    }
+
+    /// Only consider targets that have the component `F`, multiple of these on a decision are
+    /// unioned together.
+    pub fn targeted_filter<F: Component>() -> Self {
+        Self::filter::<F>(TargetFilterType::Include)
+    }
+
+    /// Only consider targets that have the component `F`, multiple of these on a decision are
+    /// intersected together.
+    pub fn targeted_require_filter<F: Component>() -> Self {
+        Self::filter::<F>(TargetFilterType::Require)
+    }
+
+    /// Do not consider targets that have the component `F`.
+    pub fn targeted_exclude_filter<F: Component>() -> Self {
+        Self::filter::<F>(TargetFilterType::Exclude)
+    }
+
+    /// Applies a targeted filter to the target at this position of the decision's multi
+    /// targeted considerations, rather than to the target of its targeted considerations.
+    /// Positions past the input's number of targets are ignored.
+    pub fn for_target(self, slot: usize) -> Self {
+        if !matches!(
+            self.consideration_type,
+            ConsiderationType::TargetedFilter(_)
+        ) {
+            panic!("Only targeted filters can be applied to one of the targets of multi targeted considerations!")
+        }
         Self {
-            input_name: type_name_of(input).into(),
-            input: input as usize,
-            response_curve: Box::new(LinearCurve::new(1.0)),
-            consideration_type: ConsiderationType::Simple,
-            system_app_config: Some(input.into_app_config()),
+            target_slot: Some(slot),
+            ..self
         }
     }
 
-    pub fn targeted<Q1: WorldQuery + 'static, Q2: WorldQuery + 'static>(
-        input: fn(Query<Q1>, Query<Q2>, Res<AIDefinitions>, Res<AITargetEntitySets>),
+    /// Only consider the targets chosen by a system generated by the target_selector macro,
+    /// this allows a decision to supply its own candidate targets from arbitrary queries. It can
+    /// be combined with filters, in which case targets must be selected and pass the filters. A
+    /// decision can have at most one target selector.
+    pub fn target_selector<M: InputMarker>(
+        selector: impl IntoSystemAppConfig<M> + Copy,
+    ) -> Self {
+        Self::input_system(selector, ConsiderationType::TargetSelector)
+    }
+
+    fn input_system<M: InputMarker, F: IntoSystemAppConfig<M> + Copy>(
+        input: F,
+        consideration_type: ConsiderationType,
     ) -> Self {
         Self {
-            input_name: type_name_of(input).into(),
-            input: input as usize,
-            response_curve: Box::new(LinearCurve::new(1.0)),
-            consideration_type: ConsiderationType::Targeted,
+            input_name: input_name_of(input).into(),
+            input: InputId::of(input),
+            response_curve: Arc::new(LinearCurve::new(1.0)),
+            input_range: None,
+            spatial_filter: None,
+            cache_max_age: None,
+            target_slot: None,
+            consideration_type,
+            id_name: input_name_of(input).into(),
+            system_name: type_name::<F>(),
             system_app_config: Some(input.into_app_config()),
         }
     }
 
-    pub fn targeted_filter<F: Component>() -> Self {
+    fn filter<F: Component>(filter_type: TargetFilterType) -> Self {
         let input = inclusive_filter_input::<F>;
         Self {
-            input_name: format!("targeted_filter_{}", type_name::<F>()),
-            input: input as usize,
-            response_curve: Box::new(LinearCurve::new(1.0)),
-            consideration_type: ConsiderationType::TargetedFilter,
+            input_name: filter_input_name::<F>(),
+            input: InputId::filter::<F>(),
+            response_curve: Arc::new(LinearCurve::new(1.0)),
+            input_range: None,
+            spatial_filter: None,
+            cache_max_age: None,
+            target_slot: None,
+            consideration_type: ConsiderationType::TargetedFilter(filter_type),
+            id_name: filter_input_name::<F>(),
+            system_name: type_name::<F>(),
             system_app_config: Some(input.into_app_config()),
         }
     }
 
     pub fn with_response_curve(self, response_curve: impl ResponseCurve + 'static) -> Self {
-        if self.consideration_type == ConsiderationType::TargetedFilter {
-            panic!("Changing the response curve of a targeted filter is not supported!")
+        if !self.is_scored() {
+            panic!("Changing the response curve of a targeted filter or target selector is not supported!")
+        }
+        Self {
+            response_curve: Arc::new(response_curve),
+            ..self
+        }
+    }
+
+    /// Normalises the raw input from min..max onto 0..1 before the response curve is applied,
+    /// inputs outside the range are clamped. The max must be greater than the min, and both
+    /// must be finite.
+    pub fn with_input_range(self, min: f32, max: f32) -> Self {
+        self.set_input_range(InputRange::Linear { min, max })
+    }
+
+    /// Like with_input_range but normalises on a log scale, this is useful for inputs that span
+    /// several orders of magnitude. Both min and max must be positive.
+    pub fn with_log_input_range(self, min: f32, max: f32) -> Self {
+        self.set_input_range(InputRange::Log { min, max })
+    }
+
+    fn set_input_range(self, input_range: InputRange) -> Self {
+        if !self.is_scored() {
+            panic!("Changing the input range of a targeted filter or target selector is not supported!")
+        }
+        // the same check as for input ranges loaded from an AIDefinitionAsset
+        if let Err(err) = input_range.validate() {
+            panic!("{}!", err)
         }
         Self {
-            response_curve: Box::new(response_curve),
+            input_range: Some(input_range),
             ..self
         }
     }
 
+    /// Only score targets within the distance of the subject, this requires a spatial index to
+    /// have been added with AISpatialIndexAppExt::add_ai_spatial_index. Subjects that aren't in
+    /// the index consider every target.
+    pub fn with_max_distance(self, distance: f32) -> Self {
+        self.set_spatial_filter(SpatialFilter::WithinDistance(distance))
+    }
+
+    /// Only score the `count` targets closest to the subject, targets are counted after the
+    /// decision's filter sets have been applied. See with_max_distance.
+    pub fn with_nearest_targets(self, count: usize) -> Self {
+        self.set_spatial_filter(SpatialFilter::Nearest(count))
+    }
+
+    pub(crate) fn set_spatial_filter(self, spatial_filter: SpatialFilter) -> Self {
+        if self.consideration_type != ConsiderationType::Targeted {
+            panic!("Spatial filters are only supported on targeted considerations!")
+        }
+        Self {
+            spatial_filter: Some(spatial_filter),
+            ..self
+        }
+    }
+
+    /// Only recalculate the input's score when one of the components it reads has changed, or
+    /// when the score has been reused `max_age` times, otherwise the score from the last
+    /// evaluation is used. This is useful for expensive inputs over components that rarely
+    /// change. If the input is used by several considerations of an AI, it is cached with the
+    /// shortest max age of those that are cached.
+    pub fn with_cache(self, max_age: u32) -> Self {
+        if !self.is_scored() {
+            panic!("Caching a targeted filter or target selector is not supported!")
+        }
+        if self.consideration_type == ConsiderationType::MultiTargeted {
+            panic!("Caching a multi targeted input is not supported!")
+        }
+        Self {
+            cache_max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    /// Sets the name shown for this consideration in logs and tools, note this does not change
+    /// the consideration's InputId.
     pub fn set_input_name(self, input_name: String) -> Self {
         Self { input_name, ..self }
     }
+
+    /// Returns false for targeted filters and target selectors, as they restrict the targets of
+    /// a decision rather than contribute to its score.
+    fn is_scored(&self) -> bool {
+        matches!(
+            self.consideration_type,
+            ConsiderationType::Simple
+                | ConsiderationType::Targeted
+                | ConsiderationType::MultiTargeted
+        )
+    }
+
+    /// Returns the consideration score for the raw input, this is the normalised input
+    /// transformed by the response curve, clamped between 0.0 and 1.0. NaN scores 0.0.
+    pub fn score(&self, input: f32) -> f32 {
+        let input = match &self.input_range {
+            Some(input_range) => input_range.normalise(input),
+            None => input,
+        };
+        let score = self.response_curve.transform(input);
+        // NaN would otherwise sort above every other score
+        if score.is_nan() {
+            0.0
+        } else {
+            score.clamp(0.0, 1.0)
+        }
+    }
 }
@@ -1,9 +1,15 @@
-use crate::considerations::{Consideration, ConsiderationType};
+use crate::action_factory::{ActionContext, ActionFactory};
+use crate::considerations::{Consideration, ConsiderationType, TargetFilterType};
 use crate::plugin::UtililityAISet;
+use crate::registry::AIRegistry;
+use crate::selection::SelectionStrategy;
 use crate::systems::ensure_entity_has_ai_meta;
-use crate::{AIDefinition, AIDefinitions, Decision};
+use crate::{
+    AIDefinition, AIDefinitions, Commitment, Decision, DecisionBucket, FailurePenalty,
+    InputId, TargetFilter,
+};
 use bevy::app::{App, AppTypeRegistry};
-use bevy::prelude::{Component, IntoSystemConfig, Resource};
+use bevy::prelude::{Component, IntoSystemConfig, Resource, World};
 use bevy::reflect::{GetTypeRegistration, TypeRegistration};
 use bevy::utils::{HashMap, HashSet};
 use std::any::{type_name, TypeId};
@@ -16,14 +22,106 @@ pub struct DefineAI<T: Component> {
     /// The decisions that make up this AI's logic, passed to AIDefinition on register.
     decisions: Vec<Decision>,
     /// The full set of required inputs for this AI, passed to AIDefinition on register.
-    required_inputs: HashSet<usize>,
-    /// A map of targeted_input system to the filter sets required for it, passed to AIDefinition on register.
-    targeted_input_filter_sets: HashMap<usize, Vec<usize>>,
+    required_inputs: HashSet<InputId>,
+    /// A map of targeted_input system to the filter required for it, which is the same for every
+    /// decision, passed to AIDefinition on register.
+    targeted_input_filter_sets: HashMap<InputId, TargetFilter>,
+    /// A map of multi_targeted_input system to the filters required for each of its targets,
+    /// passed to AIDefinition on register.
+    multi_targeted_input_filter_sets: HashMap<InputId, Vec<TargetFilter>>,
     /// A vec of all actions defined as part of this AI, will be registered to the App.
     action_type_registrations: Vec<TypeRegistration>,
+    /// The bonus applied to the current action's score, passed to AIDefinition on register.
+    momentum: f32,
+    /// The margin a new option must beat the current one by, passed to AIDefinition on register.
+    switching_margin: f32,
+    /// Whether consideration scores are compensated, passed to AIDefinition on register.
+    compensate_considerations: bool,
+    /// How the AI picks between its evaluated decisions, passed to AIDefinition on register.
+    selection_strategy: SelectionStrategy,
+    /// The penalty applied to failed actions, passed to AIDefinition on register.
+    failure_penalty: FailurePenalty,
+    /// The number of ticks between evaluations, passed to AIDefinition on register.
+    decision_interval: u32,
+    /// A map of cached input to the max age of its scores, passed to AIDefinition on register.
+    cached_inputs: HashMap<InputId, u32>,
+    /// The buckets decisions can be grouped into, passed to AIDefinition on register.
+    buckets: Vec<DecisionBucket>,
     marker_phantom: PhantomData<T>,
 }
 
+/// Optional per-decision settings, see DefineAI::add_decision_with_settings.
+#[derive(Clone)]
+pub struct DecisionSettings {
+    /// Overrides the AI's momentum for this decision, see DefineAI::set_momentum.
+    pub momentum: Option<f32>,
+    /// Multiplies the decision's score, defaults to 1.0.
+    pub weight: f32,
+    /// How long the action must be kept before the entity makes a new decision.
+    pub min_commitment: Option<Commitment>,
+    /// Overrides the AI's consideration compensation for this decision, see
+    /// DefineAI::set_compensate_considerations.
+    pub compensate_considerations: Option<bool>,
+    /// Builds the action component from the context it was chosen in, see
+    /// DecisionSettings::with_action_factory.
+    pub action_factory: Option<ActionFactory>,
+    /// The bucket the decision is in, see DefineAI::add_bucket.
+    pub bucket: Option<String>,
+}
+
+impl Default for DecisionSettings {
+    fn default() -> Self {
+        Self {
+            momentum: None,
+            weight: 1.0,
+            min_commitment: None,
+            compensate_considerations: None,
+            action_factory: None,
+            bucket: None,
+        }
+    }
+}
+
+impl DecisionSettings {
+    pub fn with_momentum(mut self, momentum: f32) -> Self {
+        self.momentum = Some(momentum);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_min_commitment(mut self, min_commitment: Commitment) -> Self {
+        self.min_commitment = Some(min_commitment);
+        self
+    }
+
+    pub fn with_compensate_considerations(mut self, compensate_considerations: bool) -> Self {
+        self.compensate_considerations = Some(compensate_considerations);
+        self
+    }
+
+    /// Builds the action component from the subject, target and score of the decision, rather
+    /// than with its Default implementation. This allows actions to carry parameters such as
+    /// where to move to. The action is rebuilt when the decision switches target. The factory
+    /// must build the decision's action.
+    pub fn with_action_factory<C: Component>(
+        mut self,
+        factory: impl Fn(&ActionContext) -> C + Send + Sync + 'static,
+    ) -> Self {
+        self.action_factory = Some(ActionFactory::new(factory));
+        self
+    }
+
+    /// Puts the decision in the named bucket, which must be added with DefineAI::add_bucket.
+    pub fn with_bucket(mut self, bucket: impl Into<String>) -> Self {
+        self.bucket = Some(bucket.into());
+        self
+    }
+}
+
 impl<T: Component> DefineAI<T> {
     pub fn new() -> DefineAI<T> {
         Self {
@@ -31,112 +129,461 @@ impl<T: Component> DefineAI<T> {
             decisions: Vec::new(),
             required_inputs: HashSet::new(),
             targeted_input_filter_sets: HashMap::new(),
+            multi_targeted_input_filter_sets: HashMap::new(),
             action_type_registrations: Vec::new(),
+            momentum: 0.0,
+            switching_margin: 0.0,
+            compensate_considerations: false,
+            selection_strategy: SelectionStrategy::default(),
+            failure_penalty: FailurePenalty::default(),
+            decision_interval: 1,
+            cached_inputs: HashMap::new(),
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Sets the bonus applied to the score of the entity's current action and target when
+    /// making decisions, e.g. 0.25 boosts it by 25%. This stops entities flip-flopping
+    /// between similarly scored options. Defaults to 0.0.
+    pub fn set_momentum(self, momentum: f32) -> DefineAI<T> {
+        Self { momentum, ..self }
+    }
+
+    /// Sets the amount that a new option's score must beat the current action's score by
+    /// (after momentum is applied) before the entity will switch to it. Defaults to 0.0.
+    pub fn set_switching_margin(self, switching_margin: f32) -> DefineAI<T> {
+        Self {
+            switching_margin,
+            ..self
+        }
+    }
+
+    /// Enables the compensation factor for consideration scores. As consideration scores are
+    /// multiplied together decisions with more considerations tend to score lower, this makes
+    /// up some of the difference so that they can compete fairly. Defaults to false.
+    pub fn set_compensate_considerations(
+        self,
+        compensate_considerations: bool,
+    ) -> DefineAI<T> {
+        Self {
+            compensate_considerations,
+            ..self
         }
     }
 
+    /// Sets how the AI picks between its evaluated decisions, see SelectionStrategy. Random
+    /// strategies draw from the AIRng resource. Defaults to SelectionStrategy::Highest.
+    pub fn set_selection_strategy(self, selection_strategy: SelectionStrategy) -> DefineAI<T> {
+        Self {
+            selection_strategy,
+            ..self
+        }
+    }
+
+    /// Sets the multiplier applied to the score of an action and target pair after the action
+    /// reports ActionStatus::Failure, and how long it lasts for. Defaults to 0.5 for 5 seconds.
+    pub fn set_failure_penalty(self, multiplier: f32, duration: Commitment) -> DefineAI<T> {
+        Self {
+            failure_penalty: FailurePenalty {
+                multiplier,
+                duration,
+            },
+            ..self
+        }
+    }
+
+    /// Sets the number of ticks between evaluations of each entity, defaults to 1 which
+    /// evaluates every entity on every tick. Entities are staggered across the interval, so with
+    /// an interval of 10 only a tenth of the entities have their inputs calculated and their
+    /// decisions made each tick. Entities without an action, or whose action has succeeded or
+    /// failed, are evaluated on the next tick regardless.
+    pub fn set_decision_interval(self, decision_interval: u32) -> DefineAI<T> {
+        Self {
+            decision_interval,
+            ..self
+        }
+    }
+
+    /// Copies the registered definition of the AI so that its decisions can be changed while
+    /// the app runs, the changes are applied with DefineAI::update. The registered definition
+    /// is used until then. This needs World access, so it is typically called from an
+    /// exclusive system or a Command.
+    #[must_use = "the changes are only applied with DefineAI::update"]
+    pub fn edit(world: &World) -> DefineAI<T> {
+        let ai_definition = world
+            .resource::<AIDefinitions>()
+            .map
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .unwrap_or_else(|| panic!("No AI is defined for {}!", type_name::<T>()));
+        Self {
+            decisions: ai_definition.decisions,
+            required_inputs: ai_definition.required_inputs,
+            targeted_input_filter_sets: ai_definition.targeted_input_filter_sets,
+            multi_targeted_input_filter_sets: ai_definition.multi_targeted_input_filter_sets,
+            momentum: ai_definition.momentum,
+            switching_margin: ai_definition.switching_margin,
+            compensate_considerations: ai_definition.compensate_considerations,
+            selection_strategy: ai_definition.selection_strategy,
+            failure_penalty: ai_definition.failure_penalty,
+            decision_interval: ai_definition.decision_interval,
+            cached_inputs: ai_definition.cached_inputs,
+            buckets: ai_definition.buckets,
+            ..Self::new()
+        }
+    }
+
+    /// Adds a bucket of decisions, see DecisionSettings::with_bucket. When making a decision the
+    /// AI first picks the highest priority bucket that has a decision scoring above zero, and
+    /// then picks between the decisions in that bucket as usual. Decisions that aren't in a
+    /// bucket have a priority of 0.0, and buckets with the same priority are picked from
+    /// together.
+    pub fn add_bucket(mut self, name: impl Into<String>, priority: f32) -> DefineAI<T> {
+        let name = name.into();
+        if let Err(err) = self.validate_bucket(&name) {
+            panic!("{}!", err)
+        }
+        self.buckets.push(DecisionBucket { name, priority });
+        self
+    }
+
+    /// Returns an error if a bucket with the name can't be added to the AI.
+    pub(crate) fn validate_bucket(&self, name: &str) -> Result<(), String> {
+        if self.buckets.iter().any(|bucket| bucket.name == name) {
+            return Err(format!("A bucket named '{}' has already been added", name));
+        }
+        Ok(())
+    }
+
+    /// Adds a decision to perform the action C, scored by the considerations. Decisions with
+    /// targeted considerations are scored for each target, and their targets can be restricted
+    /// with targeted filters, spatial filters or a Consideration::target_selector.
     pub fn add_decision<C: Component + GetTypeRegistration>(
+        self,
+        considerations: Vec<Consideration>,
+    ) -> DefineAI<T> {
+        self.add_decision_with_settings::<C>(considerations, DecisionSettings::default())
+    }
+
+    /// Adds a decision whose action is built by the factory, see
+    /// DecisionSettings::with_action_factory.
+    pub fn add_decision_with_factory<C: Component + GetTypeRegistration>(
+        self,
+        considerations: Vec<Consideration>,
+        factory: impl Fn(&ActionContext) -> C + Send + Sync + 'static,
+    ) -> DefineAI<T> {
+        self.add_decision_with_settings::<C>(
+            considerations,
+            DecisionSettings::default().with_action_factory(factory),
+        )
+    }
+
+    pub fn add_decision_with_settings<C: Component + GetTypeRegistration>(
+        mut self,
+        considerations: Vec<Consideration>,
+        settings: DecisionSettings,
+    ) -> DefineAI<T> {
+        if settings
+            .action_factory
+            .as_ref()
+            .is_some_and(|action_factory| action_factory.action != TypeId::of::<C>())
+        {
+            panic!(
+                "The action factory of a decision must build its action {}!",
+                type_name::<C>()
+            )
+        }
+        self.action_type_registrations
+            .push(C::get_type_registration());
+        self.add_decision_for_action(
+            TypeId::of::<C>(),
+            type_name::<C>().into(),
+            considerations,
+            settings,
+        )
+    }
+
+    /// Removes the decisions to perform the action C, any inputs that are no longer used by the
+    /// remaining decisions are no longer calculated for this AI.
+    pub fn remove_decision<C: Component>(mut self) -> DefineAI<T> {
+        self.decisions
+            .retain(|decision| decision.action != TypeId::of::<C>());
+
+        self.required_inputs.clear();
+        self.cached_inputs.clear();
+        for consideration in self.decisions.iter().flat_map(Decision::considerations) {
+            self.required_inputs.insert(consideration.input);
+            if let Some(max_age) = consideration.cache_max_age {
+                let cached_max_age = self
+                    .cached_inputs
+                    .entry(consideration.input)
+                    .or_insert(max_age);
+                *cached_max_age = max_age.min(*cached_max_age);
+            }
+        }
+        self.targeted_input_filter_sets
+            .retain(|input, _| self.required_inputs.contains(input));
+        self.multi_targeted_input_filter_sets
+            .retain(|input, _| self.required_inputs.contains(input));
+
+        self
+    }
+
+    /// Returns a targeted input that the considerations give a different TargetFilter to the
+    /// AI's other decisions, or to another consideration. The scores of a targeted input are
+    /// shared by every decision of the AI, so every decision must filter its targets the same way.
+    fn conflicting_target_filter<'a>(
+        &self,
+        considerations: &'a [Consideration],
+    ) -> Option<&'a Consideration> {
+        let (target_filters, multi_target_filters) = target_filters(considerations);
+        let mut filter_sets = self.targeted_input_filter_sets.clone();
+        let mut multi_filter_sets = self.multi_targeted_input_filter_sets.clone();
+        let conflicting_input = target_filters
+            .into_iter()
+            .find(|(input, target_filter)| {
+                filter_sets
+                    .entry(*input)
+                    .or_insert_with(|| target_filter.clone())
+                    != target_filter
+            })
+            .map(|(input, _)| input)
+            .or_else(|| {
+                multi_target_filters
+                    .into_iter()
+                    .find(|(input, slot_filters)| {
+                        multi_filter_sets
+                            .entry(*input)
+                            .or_insert_with(|| slot_filters.clone())
+                            != slot_filters
+                    })
+                    .map(|(input, _)| input)
+            });
+        conflicting_input.and_then(|input| {
+            considerations
+                .iter()
+                .find(|consideration| consideration.input == input)
+        })
+    }
+
+    /// Returns an error if a decision with the considerations and settings can't be added to
+    /// the AI. DefineAI panics with the error, while AIDefinitionConfig::build returns it so
+    /// that an invalid asset doesn't crash the app.
+    pub(crate) fn validate_decision(
+        &self,
+        considerations: &[Consideration],
+        settings: &DecisionSettings,
+    ) -> Result<(), String> {
+        if !settings.weight.is_finite() || settings.weight < 0.0 {
+            return Err("The weight of a decision must be finite and not negative".into());
+        }
+        if settings
+            .momentum
+            .is_some_and(|momentum| !momentum.is_finite())
+        {
+            return Err("The momentum of a decision must be finite".into());
+        }
+
+        if let Some(input) = self.conflicting_target_filter(considerations) {
+            return Err(format!(
+                "The targeted input '{}' has different filters in different decisions, \
+                targeted inputs must have the same filters in every decision of an AI",
+                input.input_name
+            ));
+        }
+
+        let count = |consideration_type: ConsiderationType| {
+            considerations
+                .iter()
+                .filter(|consideration| consideration.consideration_type == consideration_type)
+                .count()
+        };
+        let has_targeted = count(ConsiderationType::Targeted) > 0;
+        let has_multi_targeted = count(ConsiderationType::MultiTargeted) > 0;
+        let target_selectors = count(ConsiderationType::TargetSelector);
+        let filters: Vec<&Consideration> = considerations
+            .iter()
+            .filter(|consideration| {
+                matches!(
+                    consideration.consideration_type,
+                    ConsiderationType::TargetedFilter(_)
+                )
+            })
+            .collect();
+
+        if target_selectors > 1 {
+            return Err("Decisions can only have one Consideration::target_selector".into());
+        }
+        if !filters.is_empty() && !has_targeted && !has_multi_targeted {
+            return Err(
+                "Decisions that have Consideration::targeted_filter considerations without \
+                any Consideration::targeted considerations are invalid"
+                    .into(),
+            );
+        }
+        if filters
+            .iter()
+            .any(|filter| filter.target_slot.is_some() != has_multi_targeted)
+        {
+            return Err(
+                "Targeted filters must use Consideration::for_target in decisions with \
+                Consideration::multi_targeted considerations, and only in those decisions"
+                    .into(),
+            );
+        }
+        if target_selectors > 0 && !has_targeted {
+            return Err(
+                "Decisions that have a Consideration::target_selector without any \
+                Consideration::targeted considerations are invalid"
+                    .into(),
+            );
+        }
+        if has_targeted && has_multi_targeted {
+            return Err("Decisions that have both Consideration::targeted and \
+                Consideration::multi_targeted considerations are invalid"
+                .into());
+        }
+        Ok(())
+    }
+
+    /// Adds a decision for an action that is identified by its TypeId, the action must already be
+    /// registered with the AppTypeRegistry.
+    pub(crate) fn add_decision_for_action(
         mut self,
+        action: TypeId,
+        action_name: String,
         considerations: Vec<Consideration>,
-        // target_filter_here
+        settings: DecisionSettings,
     ) -> DefineAI<T> {
+        if let Err(err) = self.validate_decision(&considerations, &settings) {
+            panic!("{}!", err)
+        }
+
         let mut simple_considerations = Vec::new();
         let mut targeted_filter_considerations = Vec::new();
         let mut targeted_considerations = Vec::new();
+        let mut multi_targeted_considerations = Vec::new();
+        let mut target_selector: Option<Consideration> = None;
 
         considerations.into_iter().for_each(|consideration| {
             self.required_inputs.insert(consideration.input);
+            if let Some(max_age) = consideration.cache_max_age {
+                let cached_max_age = self
+                    .cached_inputs
+                    .entry(consideration.input)
+                    .or_insert(max_age);
+                *cached_max_age = max_age.min(*cached_max_age);
+            }
             match consideration.consideration_type {
                 ConsiderationType::Simple => simple_considerations.push(consideration),
                 ConsiderationType::Targeted => targeted_considerations.push(consideration),
-                ConsiderationType::TargetedFilter => {
+                ConsiderationType::TargetedFilter(_) => {
                     targeted_filter_considerations.push(consideration)
                 }
+                ConsiderationType::TargetSelector => target_selector = Some(consideration),
+                ConsiderationType::MultiTargeted => {
+                    multi_targeted_considerations.push(consideration)
+                }
             }
         });
 
-        if !targeted_filter_considerations.is_empty() && targeted_considerations.is_empty() {
-            panic!(
-                "Decisions that have Consideration::targeted_filter considerations without \
-                any Consideration::targeted considerations are invalid!"
-            )
-        }
-
-        let is_targeted = !targeted_considerations.is_empty();
+        let is_targeted =
+            !targeted_considerations.is_empty() || !multi_targeted_considerations.is_empty();
 
         // Add any filter considerations to the AIDefinition
-        if is_targeted && !targeted_filter_considerations.is_empty() {
-            let filter_sets: Vec<usize> = targeted_filter_considerations
+        let (target_filters, multi_target_filters) = target_filters(
+            target_selector
                 .iter()
-                .map(|f| f.input)
-                .collect();
-            for targeted_consideration in &targeted_considerations {
-                self.targeted_input_filter_sets
-                    .insert(targeted_consideration.input, filter_sets.clone());
-            }
-        }
+                .chain(&targeted_filter_considerations)
+                .chain(&targeted_considerations)
+                .chain(&multi_targeted_considerations),
+        );
+        self.targeted_input_filter_sets.extend(target_filters);
+        self.multi_targeted_input_filter_sets
+            .extend(multi_target_filters);
 
         let decision = Decision {
-            action_name: type_name::<C>().into(),
-            action: TypeId::of::<C>(),
+            action_name,
+            action,
             simple_considerations,
             targeted_considerations,
             targeted_filter_considerations,
+            multi_targeted_considerations,
+            target_selector,
             is_targeted,
+            momentum: settings.momentum,
+            weight: settings.weight,
+            min_commitment: settings.min_commitment,
+            compensate_considerations: settings.compensate_considerations,
+            action_factory: settings.action_factory,
+            bucket: settings.bucket,
         };
 
-        self.action_type_registrations
-            .push(C::get_type_registration());
         self.decisions.push(decision);
 
         self
     }
 
+    /// Returns an error if the decisions can't be built into an AIDefinition, the checks that
+    /// depend on a single decision are done as it is added, see DefineAI::validate_decision.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let has_bucket = |decision: &&Decision| {
+            decision
+                .bucket
+                .as_ref()
+                .is_none_or(|name| self.buckets.iter().any(|bucket| &bucket.name == name))
+        };
+        if let Some(decision) = self.decisions.iter().find(|d| !has_bucket(d)) {
+            return Err(format!(
+                "Decision for action '{}' is in the bucket '{}' which hasn't been added, see \
+                DefineAI::add_bucket",
+                decision.action_name,
+                decision.bucket.as_deref().unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Consumes the builder and returns the AIDefinition, note this doesn't add any systems or
+    /// register any actions with the App.
+    pub(crate) fn build(self) -> AIDefinition {
+        if let Err(err) = self.validate() {
+            panic!("{}!", err)
+        }
+        AIDefinition {
+            decisions: self.decisions,
+            required_inputs: self.required_inputs,
+            targeted_input_filter_sets: self.targeted_input_filter_sets,
+            multi_targeted_input_filter_sets: self.multi_targeted_input_filter_sets,
+            momentum: self.momentum,
+            switching_margin: self.switching_margin,
+            compensate_considerations: self.compensate_considerations,
+            selection_strategy: self.selection_strategy,
+            failure_penalty: self.failure_penalty,
+            decision_interval: self.decision_interval,
+            cached_inputs: self.cached_inputs,
+            buckets: self.buckets,
+        }
+    }
+
     /// Registers the defined AI against the bevy App, this should be called as the last step of
     /// the defineAI process.
     pub fn register(mut self, app: &mut App) {
-        // note all these actions are idempotent except app.add_system, so we maintain a resource on
-        // the app to track systems that are already added.
-        {
-            let mut added_systems = app
-                .world
-                .remove_resource::<AddedSystemTracker>()
-                .unwrap_or_else(|| {
-                    panic!("Make sure the plugin is added to the app before calls to DefineAI")
-                });
-
-            app.add_system(ensure_entity_has_ai_meta::<T>);
-
-            // Add utility systems
-            for decision in &mut self.decisions {
-                decision
-                    .simple_considerations
-                    .iter_mut()
-                    .chain(decision.targeted_considerations.iter_mut())
-                    .chain(decision.targeted_filter_considerations.iter_mut())
-                    .for_each(|c| {
-                        let system_app_config = c.system_app_config.take().unwrap();
-                        if !added_systems.systems.contains(&c.input) {
-                            app.add_system(
-                                system_app_config.in_set(UtililityAISet::CalculateInputs),
-                            );
-                            added_systems.systems.insert(c.input);
-                        }
-                    });
-            }
-            app.add_system(ensure_entity_has_ai_meta::<T>);
+        app.add_system(ensure_entity_has_ai_meta::<T>);
 
-            app.world.insert_resource(added_systems);
+        // Add utility systems
+        for decision in &mut self.decisions {
+            decision
+                .simple_considerations
+                .iter_mut()
+                .chain(decision.targeted_considerations.iter_mut())
+                .chain(decision.targeted_filter_considerations.iter_mut())
+                .chain(decision.multi_targeted_considerations.iter_mut())
+                .chain(decision.target_selector.iter_mut())
+                .for_each(|c| add_input_system(app, c));
         }
 
-        // Register actions with the AppTypeRegistry
-        {
-            let registry = app.world.resource_mut::<AppTypeRegistry>();
-            let mut registry_write = registry.write();
-            self.action_type_registrations
-                .into_iter()
-                .for_each(|f| registry_write.add_registration(f));
-        }
+        self.register_actions(&mut app.world);
 
         // Add the AIDefinition to the AIDefinitions resource
         let mut ai_definitions = app
@@ -146,21 +593,175 @@ impl<T: Component> DefineAI<T> {
                 panic!("Make sure the plugin is added to the app before calls to DefineAI")
             });
         if !ai_definitions.map.contains_key(&TypeId::of::<T>()) {
-            ai_definitions.map.insert(
-                TypeId::of::<T>(),
-                AIDefinition {
-                    decisions: self.decisions,
-                    required_inputs: self.required_inputs,
-                    targeted_input_filter_sets: self.targeted_input_filter_sets,
-                },
-            );
+            ai_definitions.map.insert(TypeId::of::<T>(), self.build());
+        } else {
+            panic!(
+                "AI is already defined for this marker component, use DefineAI::edit to change \
+                it!"
+            )
+        }
+    }
+
+    /// Replaces the AI's definition while the app runs, see DefineAI::edit. Unlike register no
+    /// systems are added, so the inputs of any new decisions must already have been added to
+    /// the App, either by registering another AI that uses them or with RegisterAIAppExt.
+    /// Entities whose current action was removed switch to a new one when they are next
+    /// evaluated.
+    pub fn update(mut self, world: &mut World) {
+        let added_systems = world
+            .get_resource::<AddedSystemTracker>()
+            .unwrap_or_else(|| {
+                panic!("Make sure the plugin is added to the app before calls to DefineAI")
+            });
+        if let Some(consideration) = self
+            .decisions
+            .iter()
+            .flat_map(Decision::considerations)
+            .find(|consideration| !added_systems.systems.contains_key(&consideration.input))
+        {
+            panic!(
+                "The input system for '{}' hasn't been added to the App, inputs must be added \
+                before the AI is updated!",
+                consideration.input_name
+            )
+        }
+
+        self.register_actions(world);
+
+        world
+            .resource_mut::<AIDefinitions>()
+            .map
+            .insert(TypeId::of::<T>(), self.build());
+    }
+
+    /// Registers the actions of the decisions with the AppTypeRegistry.
+    fn register_actions(&mut self, world: &mut World) {
+        let registry = world.resource_mut::<AppTypeRegistry>();
+        let mut registry_write = registry.write();
+        std::mem::take(&mut self.action_type_registrations)
+            .into_iter()
+            .for_each(|f| registry_write.add_registration(f));
+    }
+}
+
+/// The TargetFilters of a decision's targeted and multi targeted inputs, see target_filters.
+type TargetFilters = (
+    Vec<(InputId, TargetFilter)>,
+    Vec<(InputId, Vec<TargetFilter>)>,
+);
+
+/// Returns the TargetFilter each targeted consideration of a decision scores its targets with,
+/// and the TargetFilters each multi targeted consideration scores each of its targets with.
+/// Decisions without filters still get empty TargetFilters, so that they can be checked
+/// against the filters of the AI's other decisions.
+fn target_filters<'a>(
+    considerations: impl IntoIterator<Item = &'a Consideration>,
+) -> TargetFilters {
+    let mut target_filter = TargetFilter::default();
+    let mut slot_filters: Vec<TargetFilter> = Vec::new();
+    let mut targeted_considerations = Vec::new();
+    let mut multi_targeted_considerations = Vec::new();
+    for consideration in considerations {
+        let filter_type = match consideration.consideration_type {
+            ConsiderationType::TargetedFilter(filter_type) => filter_type,
+            ConsiderationType::Targeted => {
+                targeted_considerations.push(consideration);
+                continue;
+            }
+            ConsiderationType::MultiTargeted => {
+                multi_targeted_considerations.push(consideration);
+                continue;
+            }
+            ConsiderationType::TargetSelector => {
+                target_filter.selector = Some(consideration.input);
+                continue;
+            }
+            ConsiderationType::Simple => continue,
+        };
+        let filter = match consideration.target_slot {
+            Some(slot) => {
+                if slot_filters.len() <= slot {
+                    slot_filters.resize(slot + 1, TargetFilter::default());
+                }
+                &mut slot_filters[slot]
+            }
+            None => &mut target_filter,
+        };
+        match filter_type {
+            TargetFilterType::Include => filter.include.push(consideration.input),
+            TargetFilterType::Require => filter.require.push(consideration.input),
+            TargetFilterType::Exclude => filter.exclude.push(consideration.input),
+        }
+    }
+    // sorted so that the same filters in a different order are equal
+    for filter in std::iter::once(&mut target_filter).chain(&mut slot_filters) {
+        filter.include.sort();
+        filter.require.sort();
+        filter.exclude.sort();
+    }
+
+    let target_filters = targeted_considerations
+        .into_iter()
+        .map(|targeted_consideration| {
+            let target_filter = TargetFilter {
+                spatial: targeted_consideration.spatial_filter,
+                ..target_filter.clone()
+            };
+            (targeted_consideration.input, target_filter)
+        })
+        .collect();
+    let multi_target_filters = multi_targeted_considerations
+        .into_iter()
+        .map(|multi_targeted_consideration| {
+            (multi_targeted_consideration.input, slot_filters.clone())
+        })
+        .collect();
+    (target_filters, multi_target_filters)
+}
+
+/// Adds the consideration's input system to the App if it hasn't been added already.
+pub(crate) fn add_input_system(app: &mut App, consideration: &mut Consideration) {
+    // note adding the same system twice would run it twice, so we maintain a resource on the app
+    // to track systems that are already added.
+    let mut added_systems = app
+        .world
+        .remove_resource::<AddedSystemTracker>()
+        .unwrap_or_else(|| {
+            panic!("Make sure the plugin is added to the app before calls to DefineAI")
+        });
+
+    if let Some(system_app_config) = consideration.system_app_config.take() {
+        if let Some(system_name) = added_systems.systems.get(&consideration.input) {
+            if *system_name != consideration.system_name {
+                panic!(
+                    "The input systems '{}' and '{}' have the same InputId, give one of them a \
+                    different name with #[input_system(name = \"...\")]!",
+                    system_name, consideration.system_name
+                )
+            }
         } else {
-            panic!("AI is already defined for this marker component!")
+            // filter sets and selected targets must be up to date before the targeted inputs
+            // read them
+            let set = match consideration.consideration_type {
+                ConsiderationType::TargetedFilter(_) => UtililityAISet::UpdateTargetFilters,
+                ConsiderationType::TargetSelector => UtililityAISet::SelectTargets,
+                _ => UtililityAISet::CalculateInputs,
+            };
+            app.add_system(system_app_config.in_set(set));
+            added_systems
+                .systems
+                .insert(consideration.input, consideration.system_name);
         }
     }
+
+    app.world.insert_resource(added_systems);
+
+    app.world
+        .resource_mut::<AIRegistry>()
+        .insert_input_name(consideration.input, &consideration.id_name);
 }
 
 #[derive(Resource, Default)]
 pub(crate) struct AddedSystemTracker {
-    pub(crate) systems: HashSet<usize>,
+    pub(crate) systems: HashMap<InputId, &'static str>,
 }
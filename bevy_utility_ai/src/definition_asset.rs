@@ -0,0 +1,607 @@
+use crate::considerations::{Consideration, ConsiderationType, InputRange, TargetFilterType};
+use crate::define_ai::{DecisionSettings, DefineAI};
+use crate::plugin::UtililityAISet;
+use crate::registry::AIRegistry;
+use crate::response_curves::{
+    validate_clamped_range, validate_remapped_range, ExponentialDecayCurve, InverseCurve,
+    LinearCurve, LogisticCurve, NormalisedLogitCurve, PiecewiseLinearCurve, PolynomialCurve,
+    ResponseCurve, SineCurve, StepCurve,
+};
+use crate::selection::SelectionStrategy;
+use crate::spatial::SpatialFilter;
+use crate::systems::ensure_entity_has_ai_meta;
+use crate::{AIDefinition, AIDefinitions, Commitment, DecisionBucket, FailurePenalty};
+use bevy::asset::{
+    AddAsset, AssetEvent, AssetLoader, AssetServer, Assets, Handle, LoadContext, LoadedAsset,
+};
+use bevy::log::{error, info};
+use bevy::prelude::{App, Component, EventReader, IntoSystemConfig, Res, ResMut, Resource};
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+
+/// A serialisable description of an AI, see AIDefinitionAsset. Inputs, filters and actions are
+/// referred to by the name they were registered with in the AIRegistry.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AIDefinitionConfig {
+    #[serde(default)]
+    pub momentum: f32,
+    #[serde(default)]
+    pub switching_margin: f32,
+    #[serde(default)]
+    pub compensate_considerations: bool,
+    #[serde(default)]
+    pub selection_strategy: SelectionStrategy,
+    #[serde(default)]
+    pub failure_penalty: FailurePenalty,
+    #[serde(default = "default_decision_interval")]
+    pub decision_interval: u32,
+    #[serde(default)]
+    pub buckets: Vec<DecisionBucket>,
+    pub decisions: Vec<DecisionConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DecisionConfig {
+    pub action: String,
+    pub considerations: Vec<ConsiderationConfig>,
+    #[serde(default)]
+    pub momentum: Option<f32>,
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    #[serde(default)]
+    pub min_commitment: Option<Commitment>,
+    #[serde(default)]
+    pub compensate_considerations: Option<bool>,
+    #[serde(default)]
+    pub bucket: Option<String>,
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_decision_interval() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ConsiderationConfig {
+    /// A simple or targeted input, see Consideration::simple and Consideration::targeted.
+    Input {
+        input: String,
+        #[serde(default)]
+        response_curve: Option<ResponseCurveConfig>,
+        #[serde(default)]
+        input_range: Option<InputRange>,
+        #[serde(default)]
+        spatial_filter: Option<SpatialFilter>,
+        #[serde(default)]
+        cache_max_age: Option<u32>,
+    },
+    /// A targeted filter, see Consideration::targeted_filter.
+    Filter {
+        component: String,
+        #[serde(default)]
+        filter_type: TargetFilterType,
+        /// The target of multi targeted inputs the filter applies to, see
+        /// Consideration::for_target.
+        #[serde(default)]
+        target: Option<usize>,
+    },
+}
+
+/// A serialisable description of a response curve, see the response_curves module.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ResponseCurveConfig {
+    Linear {
+        slope: f32,
+        #[serde(default)]
+        x_shift: f32,
+        #[serde(default)]
+        y_shift: f32,
+    },
+    Polynomial {
+        slope: f32,
+        k: f32,
+        #[serde(default)]
+        x_shift: f32,
+        #[serde(default)]
+        y_shift: f32,
+    },
+    Logistic {
+        k: f32,
+        #[serde(default)]
+        x_shift: f32,
+        #[serde(default)]
+        y_shift: f32,
+    },
+    Step {
+        threshold: f32,
+        #[serde(default)]
+        low: f32,
+        #[serde(default = "default_one")]
+        high: f32,
+    },
+    Inverse {
+        k: f32,
+        #[serde(default)]
+        x_shift: f32,
+        #[serde(default)]
+        y_shift: f32,
+    },
+    ExponentialDecay {
+        k: f32,
+        #[serde(default)]
+        x_shift: f32,
+        #[serde(default)]
+        y_shift: f32,
+    },
+    Sine {
+        amplitude: f32,
+        frequency: f32,
+        #[serde(default)]
+        x_shift: f32,
+        #[serde(default)]
+        y_shift: f32,
+    },
+    NormalisedLogit {
+        k: f32,
+    },
+    PiecewiseLinear {
+        points: Vec<(f32, f32)>,
+    },
+    Inverted(Box<ResponseCurveConfig>),
+    Clamped {
+        curve: Box<ResponseCurveConfig>,
+        min: f32,
+        max: f32,
+    },
+    Remapped {
+        curve: Box<ResponseCurveConfig>,
+        min: f32,
+        max: f32,
+    },
+    Product(Box<ResponseCurveConfig>, Box<ResponseCurveConfig>),
+    Min(Box<ResponseCurveConfig>, Box<ResponseCurveConfig>),
+    Max(Box<ResponseCurveConfig>, Box<ResponseCurveConfig>),
+}
+
+impl ResponseCurveConfig {
+    /// Returns the response curve described by the config, or an error if its parameters are
+    /// invalid.
+    pub fn to_response_curve(&self) -> Result<Box<dyn ResponseCurve>, String> {
+        Ok(match self {
+            ResponseCurveConfig::Linear {
+                slope,
+                x_shift,
+                y_shift,
+            } => Box::new(LinearCurve::new(*slope).shifted(*x_shift, *y_shift)),
+            ResponseCurveConfig::Polynomial {
+                slope,
+                k,
+                x_shift,
+                y_shift,
+            } => Box::new(PolynomialCurve::new(*slope, *k).shifted(*x_shift, *y_shift)),
+            ResponseCurveConfig::Logistic {
+                k,
+                x_shift,
+                y_shift,
+            } => Box::new(LogisticCurve::new(*k).shifted(*x_shift, *y_shift)),
+            ResponseCurveConfig::Step {
+                threshold,
+                low,
+                high,
+            } => Box::new(StepCurve::new(*threshold).with_values(*low, *high)),
+            ResponseCurveConfig::Inverse {
+                k,
+                x_shift,
+                y_shift,
+            } => Box::new(InverseCurve::new(*k).shifted(*x_shift, *y_shift)),
+            ResponseCurveConfig::ExponentialDecay {
+                k,
+                x_shift,
+                y_shift,
+            } => Box::new(ExponentialDecayCurve::new(*k).shifted(*x_shift, *y_shift)),
+            ResponseCurveConfig::Sine {
+                amplitude,
+                frequency,
+                x_shift,
+                y_shift,
+            } => Box::new(SineCurve::new(*amplitude, *frequency).shifted(*x_shift, *y_shift)),
+            ResponseCurveConfig::NormalisedLogit { k } => {
+                Box::new(NormalisedLogitCurve::new(*k))
+            }
+            ResponseCurveConfig::PiecewiseLinear { points } => {
+                if points.is_empty() {
+                    return Err("A PiecewiseLinear curve requires at least one point".into());
+                }
+                if points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+                    return Err("The points of a PiecewiseLinear curve must be finite".into());
+                }
+                Box::new(PiecewiseLinearCurve::new(points.clone()))
+            }
+            ResponseCurveConfig::Inverted(curve) => {
+                Box::new(curve.to_response_curve()?.inverted())
+            }
+            ResponseCurveConfig::Clamped { curve, min, max } => {
+                validate_clamped_range(*min, *max)?;
+                Box::new(curve.to_response_curve()?.clamped(*min, *max))
+            }
+            ResponseCurveConfig::Remapped { curve, min, max } => {
+                validate_remapped_range(*min, *max)?;
+                Box::new(curve.to_response_curve()?.remapped(*min, *max))
+            }
+            ResponseCurveConfig::Product(f, g) => {
+                Box::new(f.to_response_curve()?.multiplied_by(g.to_response_curve()?))
+            }
+            ResponseCurveConfig::Min(f, g) => {
+                Box::new(f.to_response_curve()?.min_with(g.to_response_curve()?))
+            }
+            ResponseCurveConfig::Max(f, g) => {
+                Box::new(f.to_response_curve()?.max_with(g.to_response_curve()?))
+            }
+        })
+    }
+}
+
+impl AIDefinitionConfig {
+    /// Builds the AIDefinition for the marker component T, looking up inputs, filters and
+    /// actions in the registry.
+    pub fn build<T: Component>(&self, registry: &AIRegistry) -> Result<AIDefinition, String> {
+        let mut define_ai = DefineAI::<T>::new()
+            .set_momentum(self.momentum)
+            .set_switching_margin(self.switching_margin)
+            .set_compensate_considerations(self.compensate_considerations)
+            .set_selection_strategy(self.selection_strategy)
+            .set_failure_penalty(
+                self.failure_penalty.multiplier,
+                self.failure_penalty.duration,
+            )
+            .set_decision_interval(self.decision_interval);
+
+        for bucket in &self.buckets {
+            define_ai.validate_bucket(&bucket.name)?;
+            define_ai = define_ai.add_bucket(bucket.name.clone(), bucket.priority);
+        }
+
+        for decision in &self.decisions {
+            let action = registry.action(&decision.action).ok_or_else(|| {
+                format!("No action registered with name '{}'", decision.action)
+            })?;
+
+            let considerations = decision
+                .considerations
+                .iter()
+                .map(|consideration| consideration.build(registry))
+                .collect::<Result<Vec<Consideration>, String>>()?;
+
+            let mut settings = DecisionSettings::default().with_weight(decision.weight);
+            settings.momentum = decision.momentum;
+            settings.min_commitment = decision.min_commitment;
+            settings.compensate_considerations = decision.compensate_considerations;
+            if let Some(bucket) = &decision.bucket {
+                settings = settings.with_bucket(bucket.clone());
+            }
+
+            define_ai
+                .validate_decision(&considerations, &settings)
+                .map_err(|err| {
+                    format!(
+                        "Decision for action '{}' is invalid: {}",
+                        decision.action, err
+                    )
+                })?;
+            define_ai = define_ai.add_decision_for_action(
+                action.action,
+                action.action_name.clone(),
+                considerations,
+                settings,
+            );
+        }
+
+        define_ai.validate()?;
+        Ok(define_ai.build())
+    }
+}
+
+impl ConsiderationConfig {
+    fn build(&self, registry: &AIRegistry) -> Result<Consideration, String> {
+        match self {
+            ConsiderationConfig::Input {
+                input,
+                response_curve,
+                input_range,
+                spatial_filter,
+                cache_max_age,
+            } => {
+                let mut consideration = registry
+                    .input(input)
+                    .ok_or_else(|| format!("No input registered with name '{}'", input))?;
+                if let Some(response_curve) = response_curve {
+                    consideration.response_curve = response_curve
+                        .to_response_curve()
+                        .map_err(|err| {
+                            format!("Input '{}' has an invalid curve: {}", input, err)
+                        })?
+                        .into();
+                }
+                if let Some(input_range) = input_range {
+                    input_range.validate().map_err(|err| {
+                        format!("Input '{}' has an invalid input range: {}", input, err)
+                    })?;
+                }
+                consideration.input_range = *input_range;
+                if cache_max_age.is_some()
+                    && consideration.consideration_type == ConsiderationType::MultiTargeted
+                {
+                    return Err(format!(
+                        "Input '{}' is cached but caching multi targeted inputs isn't supported",
+                        input
+                    ));
+                }
+                consideration.cache_max_age = *cache_max_age;
+                if let Some(spatial_filter) = spatial_filter {
+                    if consideration.consideration_type != ConsiderationType::Targeted {
+                        return Err(format!(
+                            "Input '{}' has a spatial filter but isn't targeted",
+                            input
+                        ));
+                    }
+                    consideration.spatial_filter = Some(*spatial_filter);
+                }
+                Ok(consideration)
+            }
+            ConsiderationConfig::Filter {
+                component,
+                filter_type,
+                target,
+            } => {
+                let mut consideration =
+                    registry.filter(component, *filter_type).ok_or_else(|| {
+                        format!("No filter registered with name '{}'", component)
+                    })?;
+                consideration.target_slot = *target;
+                Ok(consideration)
+            }
+        }
+    }
+}
+
+/// An asset that defines an AI, loaded from `.ai.ron` files. Editing the file while the app runs
+/// replaces the AIDefinition if the AssetPlugin is watching for changes.
+#[derive(TypeUuid, Debug)]
+#[uuid = "1b3f8a34-5a4e-4b8e-9c43-1f1a3b9d5e27"]
+pub struct AIDefinitionAsset {
+    pub config: AIDefinitionConfig,
+}
+
+#[derive(Default)]
+pub struct AIDefinitionAssetLoader;
+
+impl AssetLoader for AIDefinitionAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config = ron::de::from_bytes::<AIDefinitionConfig>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(AIDefinitionAsset { config }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ai.ron"]
+    }
+}
+
+/// Holds the handle of the asset that defines the AI for the marker component T.
+#[derive(Resource)]
+pub struct AIDefinitionHandle<T: Component> {
+    pub handle: Handle<AIDefinitionAsset>,
+    marker_phantom: PhantomData<T>,
+}
+
+/// Methods for defining AIs from AIDefinitionAssets.
+pub trait AIDefinitionAssetAppExt {
+    /// Loads the AI for the marker component T from an asset file, see AIDefinitionAsset.
+    fn load_ai_definition<T: Component>(&mut self, path: &str) -> &mut Self;
+
+    /// Defines the AI for the marker component T from an AIDefinitionAsset, the AIDefinition is
+    /// (re)built whenever the asset is created or modified.
+    fn add_ai_definition_asset<T: Component>(
+        &mut self,
+        handle: Handle<AIDefinitionAsset>,
+    ) -> &mut Self;
+}
+
+impl AIDefinitionAssetAppExt for App {
+    fn load_ai_definition<T: Component>(&mut self, path: &str) -> &mut Self {
+        add_ai_definition_asset_type(self);
+        let handle = self.world.resource::<AssetServer>().load(path);
+        self.add_ai_definition_asset::<T>(handle)
+    }
+
+    fn add_ai_definition_asset<T: Component>(
+        &mut self,
+        handle: Handle<AIDefinitionAsset>,
+    ) -> &mut Self {
+        add_ai_definition_asset_type(self);
+        self.insert_resource(AIDefinitionHandle::<T> {
+            handle,
+            marker_phantom: PhantomData,
+        })
+        .add_system(ensure_entity_has_ai_meta::<T>)
+        .add_system(
+            update_ai_definition_from_asset::<T>.before(UtililityAISet::ScheduleDecisions),
+        )
+    }
+}
+
+/// Adds the AIDefinitionAsset and its loader the first time an AI is defined from an asset.
+fn add_ai_definition_asset_type(app: &mut App) {
+    if !app.world.contains_resource::<Assets<AIDefinitionAsset>>() {
+        app.add_asset::<AIDefinitionAsset>()
+            .init_asset_loader::<AIDefinitionAssetLoader>();
+    }
+}
+
+/// Rebuilds the AIDefinition for T when its asset changes, invalid definitions are logged and
+/// the previous AIDefinition is kept.
+pub(crate) fn update_ai_definition_from_asset<T: Component>(
+    mut asset_events: EventReader<AssetEvent<AIDefinitionAsset>>,
+    assets: Res<Assets<AIDefinitionAsset>>,
+    definition_handle: Res<AIDefinitionHandle<T>>,
+    registry: Res<AIRegistry>,
+    mut ai_definitions: ResMut<AIDefinitions>,
+) {
+    for event in asset_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        if *handle != definition_handle.handle {
+            continue;
+        }
+        let Some(asset) = assets.get(handle) else {
+            continue;
+        };
+
+        match asset.config.build::<T>(&registry) {
+            Ok(ai_definition) => {
+                ai_definitions.map.insert(TypeId::of::<T>(), ai_definition);
+                info!("Loaded AI definition for {}", type_name::<T>());
+            }
+            Err(err) => error!(
+                "Failed to load AI definition for {}: {}",
+                type_name::<T>(),
+                err
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::considerations::{InputRange, TargetFilterType};
+    use crate::definition_asset::{
+        AIDefinitionConfig, ConsiderationConfig, ResponseCurveConfig,
+    };
+    use crate::selection::SelectionStrategy;
+
+    #[test]
+    fn ai_definition_config_parses_from_ron() {
+        let config = ron::from_str::<AIDefinitionConfig>(
+            r#"(
+                momentum: 0.25,
+                selection_strategy: WeightedRandomTopN(2),
+                buckets: [(name: "Commerce", priority: 1.0)],
+                decisions: [
+                    (
+                        action: "move_to_planet",
+                        weight: 2.0,
+                        bucket: Some("Commerce"),
+                        considerations: [
+                            Filter(component: "is_planet"),
+                            Input(
+                                input: "distance_to_planet",
+                                input_range: Some(Linear(min: 0.0, max: 100.0)),
+                                response_curve: Some(Inverted(Linear(slope: 1.0))),
+                            ),
+                        ],
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.momentum, 0.25);
+        assert_eq!(
+            config.selection_strategy,
+            SelectionStrategy::WeightedRandomTopN(2)
+        );
+        assert_eq!(config.buckets[0].name, "Commerce");
+        assert_eq!(config.buckets[0].priority, 1.0);
+        assert_eq!(config.decisions[0].weight, 2.0);
+        assert_eq!(config.decisions[0].bucket.as_deref(), Some("Commerce"));
+        assert_eq!(
+            config.decisions[0].considerations[0],
+            ConsiderationConfig::Filter {
+                component: "is_planet".into(),
+                filter_type: TargetFilterType::Include,
+                target: None,
+            }
+        );
+
+        let ConsiderationConfig::Input { response_curve, .. } =
+            &config.decisions[0].considerations[1]
+        else {
+            panic!("expected an input consideration")
+        };
+        let response_curve = response_curve.as_ref().unwrap();
+        assert_eq!(
+            *response_curve,
+            ResponseCurveConfig::Inverted(Box::new(ResponseCurveConfig::Linear {
+                slope: 1.0,
+                x_shift: 0.0,
+                y_shift: 0.0
+            }))
+        );
+        assert_eq!(
+            response_curve.to_response_curve().unwrap().transform(0.25),
+            0.75
+        );
+    }
+
+    #[test]
+    fn invalid_response_curve_configs_are_errors() {
+        let empty = ResponseCurveConfig::PiecewiseLinear { points: vec![] };
+        assert!(empty.to_response_curve().is_err());
+
+        let nested =
+            ResponseCurveConfig::Inverted(Box::new(ResponseCurveConfig::PiecewiseLinear {
+                points: vec![(0.0, 0.0), (f32::NAN, 1.0)],
+            }));
+        assert!(nested.to_response_curve().is_err());
+
+        let clamped = ResponseCurveConfig::Clamped {
+            curve: Box::new(ResponseCurveConfig::Linear {
+                slope: 1.0,
+                x_shift: 0.0,
+                y_shift: 0.0,
+            }),
+            min: 1.0,
+            max: 0.0,
+        };
+        assert!(clamped.to_response_curve().is_err());
+    }
+
+    #[test]
+    fn invalid_input_ranges_are_errors() {
+        assert!(InputRange::Linear { min: 0.0, max: 1.0 }.validate().is_ok());
+        assert!(InputRange::Linear { min: 1.0, max: 1.0 }
+            .validate()
+            .is_err());
+        assert!(InputRange::Log {
+            min: 1.0,
+            max: 10.0
+        }
+        .validate()
+        .is_ok());
+        assert!(InputRange::Log {
+            min: 0.0,
+            max: 10.0
+        }
+        .validate()
+        .is_err());
+        assert!(InputRange::Log {
+            min: 10.0,
+            max: 1.0
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use bevy::prelude::Entity;
+use std::any::TypeId;
+
+/// Sent when an entity starts a new action, after the action component has been added. This is
+/// sent after the ActionEnded event for the entity's previous action.
+#[derive(Clone, Debug)]
+pub struct ActionStarted {
+    pub entity: Entity,
+    pub action: TypeId,
+    pub action_name: String,
+    pub target: Option<Entity>,
+    /// All of the action's targets, for multi targeted actions target is the first of these.
+    pub targets: Vec<Entity>,
+    pub score: f32,
+}
+
+/// Sent when an entity stops an action, after the action component has been removed. The score
+/// is the last score of the action before the entity switched away from it.
+#[derive(Clone, Debug)]
+pub struct ActionEnded {
+    pub entity: Entity,
+    pub action: TypeId,
+    pub action_name: String,
+    pub target: Option<Entity>,
+    /// All of the action's targets, for multi targeted actions target is the first of these.
+    pub targets: Vec<Entity>,
+    pub score: f32,
+}
+
+/// Sent when an entity keeps its action but switches to different targets, after the
+/// ActionTarget and ActionTargets components have been updated. Multi targeted actions can
+/// change their targets while keeping their first target, so old_target and new_target may be
+/// the same.
+#[derive(Clone, Debug)]
+pub struct TargetChanged {
+    pub entity: Entity,
+    pub action: TypeId,
+    pub action_name: String,
+    pub old_target: Option<Entity>,
+    pub new_target: Option<Entity>,
+    pub old_targets: Vec<Entity>,
+    pub new_targets: Vec<Entity>,
+    pub score: f32,
+}
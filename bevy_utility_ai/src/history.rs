@@ -0,0 +1,184 @@
+use bevy::prelude::{Component, Entity};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::io::Write;
+
+/// Records each decision the entity's AI makes in a ring buffer, add this alongside AIMeta to
+/// the entities you want to trace. The history can be exported as JSON lines, which is useful
+/// for diffing AI behaviour between builds.
+#[derive(Component, Clone, Debug)]
+pub struct AIDecisionHistory {
+    capacity: usize,
+    records: VecDeque<DecisionRecord>,
+}
+
+/// A single evaluation of an entity's decisions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DecisionRecord {
+    /// The AITick at which the evaluation happened.
+    pub tick: u64,
+    /// Every evaluated decision, from best to worst.
+    pub scores: Vec<DecisionScore>,
+    /// The action that was chosen.
+    pub action: String,
+    #[serde(serialize_with = "serialize_target")]
+    #[serde(deserialize_with = "deserialize_target")]
+    pub target: Option<Entity>,
+    /// All of the chosen targets, for multi targeted decisions target is the first of these.
+    #[serde(serialize_with = "serialize_targets")]
+    #[serde(deserialize_with = "deserialize_targets")]
+    #[serde(default)]
+    pub targets: Vec<Entity>,
+    pub score: f32,
+    pub reason: SwitchReason,
+}
+
+/// The score of a decision for a target, targets are exported using Entity::to_bits.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DecisionScore {
+    pub action: String,
+    #[serde(serialize_with = "serialize_target")]
+    #[serde(deserialize_with = "deserialize_target")]
+    pub target: Option<Entity>,
+    /// All of the targets, for multi targeted decisions target is the first of these.
+    #[serde(serialize_with = "serialize_targets")]
+    #[serde(deserialize_with = "deserialize_targets")]
+    #[serde(default)]
+    pub targets: Vec<Entity>,
+    pub score: f32,
+}
+
+/// How the chosen decision relates to the entity's previous action.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwitchReason {
+    /// The entity switched to a different action.
+    NewAction,
+    /// The entity kept its action but switched to a different target.
+    NewTarget,
+    /// The entity kept its action and target.
+    Kept,
+}
+
+impl AIDecisionHistory {
+    /// Creates a history which keeps the most recent `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the records from oldest to newest.
+    pub fn records(&self) -> impl Iterator<Item = &DecisionRecord> {
+        self.records.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub(crate) fn push(&mut self, record: DecisionRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Writes the records from oldest to newest, as one JSON object per line.
+    pub fn write_json_lines(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for record in &self.records {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the records as JSON lines, see write_json_lines.
+    pub fn to_json_lines(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_json_lines(&mut buffer)
+            .expect("writing to a Vec can't fail");
+        String::from_utf8(buffer).expect("serde_json only writes valid UTF-8")
+    }
+}
+
+fn serialize_target<S: Serializer>(
+    target: &Option<Entity>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    target.map(Entity::to_bits).serialize(serializer)
+}
+
+fn deserialize_target<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Entity>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Entity::from_bits))
+}
+
+fn serialize_targets<S: Serializer>(
+    targets: &[Entity],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(targets.iter().map(|target| target.to_bits()))
+}
+
+fn deserialize_targets<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Entity>, D::Error> {
+    Ok(Vec::<u64>::deserialize(deserializer)?
+        .into_iter()
+        .map(Entity::from_bits)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{AIDecisionHistory, DecisionRecord, DecisionScore, SwitchReason};
+    use bevy::prelude::Entity;
+
+    fn record(tick: u64) -> DecisionRecord {
+        DecisionRecord {
+            tick,
+            scores: vec![DecisionScore {
+                action: "Action".into(),
+                target: Some(Entity::from_raw(3)),
+                targets: vec![Entity::from_raw(3), Entity::from_raw(4)],
+                score: 0.5,
+            }],
+            action: "Action".into(),
+            target: Some(Entity::from_raw(3)),
+            targets: vec![Entity::from_raw(3), Entity::from_raw(4)],
+            score: 0.5,
+            reason: SwitchReason::Kept,
+        }
+    }
+
+    #[test]
+    fn history_keeps_most_recent_records() {
+        let mut history = AIDecisionHistory::new(2);
+        history.push(record(1));
+        history.push(record(2));
+        history.push(record(3));
+
+        let ticks: Vec<u64> = history.records().map(|record| record.tick).collect();
+        assert_eq!(ticks, vec![2, 3]);
+    }
+
+    #[test]
+    fn history_exports_json_lines() {
+        let mut history = AIDecisionHistory::new(2);
+        history.push(record(1));
+        history.push(record(2));
+
+        let json_lines = history.to_json_lines();
+        let records: Vec<DecisionRecord> = json_lines
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(records, vec![record(1), record(2)]);
+    }
+}
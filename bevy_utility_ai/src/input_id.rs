@@ -0,0 +1,123 @@
+use bevy::ecs::all_tuples;
+use bevy::ecs::system::IsFunctionSystem;
+use bevy::prelude::{Component, IntoSystemAppConfig, Local};
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+
+/// A stable identifier for an input, derived from the input's name. Unlike the address of the
+/// input system it is the same across builds, so it can be saved, shown in tools or referenced
+/// from data files. Inputs are named after their module path and function name, or the name
+/// given with `#[input_system(name = "...")]`, and names must be unique within an App.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InputId(pub u64);
+
+impl InputId {
+    /// Returns the InputId for the input with this name, this is the 64 bit FNV-1a hash of the
+    /// name so that it can be evaluated at compile time.
+    pub const fn from_name(name: &str) -> Self {
+        let bytes = name.as_bytes();
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut i = 0;
+        while i < bytes.len() {
+            hash ^= bytes[i] as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+            i += 1;
+        }
+        Self(hash)
+    }
+
+    /// Returns the InputId of a system generated by one of the input system macros, this
+    /// matches the InputId the system stores its scores under.
+    pub fn of<M: InputMarker>(input_system: impl IntoSystemAppConfig<M>) -> Self {
+        Self::from_name(input_name_of(input_system))
+    }
+
+    /// Returns the InputId of the filter set for the component F, see
+    /// Consideration::targeted_filter.
+    pub fn filter<F: Component>() -> Self {
+        Self::from_name(&filter_input_name::<F>())
+    }
+}
+
+impl Display for InputId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The name of an input, the input system macros implement this for a type they generate
+/// alongside each system.
+pub trait InputName: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+/// Added to the params of the systems generated by the input system macros as a Local, so that
+/// the input's name can be read from the type of the system, see InputMarker.
+pub struct InputLabel<N: InputName>(PhantomData<fn() -> N>);
+
+impl<N: InputName> Default for InputLabel<N> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Implemented for the marker of the systems generated by the input system macros, whose first
+/// param is an InputLabel.
+pub trait InputMarker {
+    const NAME: &'static str;
+}
+
+macro_rules! impl_input_marker {
+    ($($param: ident),*) => {
+        impl<N: InputName, $($param),*> InputMarker
+            for (IsFunctionSystem, fn(Local<'static, InputLabel<N>>, $($param,)*))
+        {
+            const NAME: &'static str = N::NAME;
+        }
+    };
+}
+
+all_tuples!(impl_input_marker, 0, 15, P);
+
+/// Returns the name of an input system generated by one of the input system macros.
+pub(crate) fn input_name_of<M: InputMarker>(_: impl IntoSystemAppConfig<M>) -> &'static str {
+    M::NAME
+}
+
+/// Returns the name of the filter input for the component F, this uses the full path of F so
+/// that components with the same name in different modules don't share a filter set.
+pub(crate) fn filter_input_name<F: Component>() -> String {
+    format!("targeted_filter_{}", type_name::<F>())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input_id::{input_name_of, InputId, InputLabel, InputName};
+    use crate::AITick;
+    use bevy::prelude::{Local, Res};
+
+    #[test]
+    fn input_id_is_derived_from_input_name() {
+        struct SomeInputName;
+        impl InputName for SomeInputName {
+            const NAME: &'static str = "some_input";
+        }
+        fn some_input(_: Local<InputLabel<SomeInputName>>, _: Res<AITick>) {}
+
+        assert_eq!(input_name_of(some_input), "some_input");
+        assert_eq!(InputId::of(some_input), InputId::from_name("some_input"));
+        assert_ne!(
+            InputId::from_name("some_input"),
+            InputId::from_name("other_input")
+        );
+    }
+
+    #[test]
+    fn input_id_from_name_is_stable() {
+        // these values must never change, as InputIds may be saved or shared between builds
+        assert_eq!(InputId::from_name(""), InputId(0xcbf29ce484222325));
+        assert_eq!(InputId::from_name("a"), InputId(0xaf63dc4c8601ec8c));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::considerations::Consideration;
+use crate::plugin::UtililityAISet;
+use crate::{AIDefinitions, AIMeta, Decision};
+use bevy::prelude::{Entity, IntoSystemConfig, Plugin, Query, Res, ResMut, Resource};
+use bevy::utils::HashSet;
+use bevy_egui::egui::{self, CollapsingHeader, ComboBox, Grid, Ui};
+use bevy_egui::EguiContexts;
+
+/// Adds a window showing how the selected entity's AI scored each of its decisions, this is an
+/// alternative to reading the debug logs of make_decisions. Requires the EguiPlugin.
+pub struct AIInspectorPlugin;
+
+impl Plugin for AIInspectorPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<AIInspectorSelection>()
+            .add_system(inspector_ui.after(UtililityAISet::UpdateActions));
+    }
+}
+
+/// The entity shown in the inspector, this can be set by the game as well as from the
+/// inspector window itself.
+#[derive(Resource, Default)]
+pub struct AIInspectorSelection {
+    pub entity: Option<Entity>,
+}
+
+fn inspector_ui(
+    mut egui_contexts: EguiContexts,
+    mut selection: ResMut<AIInspectorSelection>,
+    q_ai_meta: Query<(Entity, &AIMeta)>,
+    ai_definitions: Res<AIDefinitions>,
+) {
+    egui::Window::new("Utility AI").show(egui_contexts.ctx_mut(), |ui| {
+        ComboBox::from_label("Entity")
+            .selected_text(
+                selection
+                    .entity
+                    .map_or("None".into(), |entity| format!("{:?}", entity)),
+            )
+            .show_ui(ui, |ui| {
+                for (entity, _) in q_ai_meta.iter() {
+                    ui.selectable_value(
+                        &mut selection.entity,
+                        Some(entity),
+                        format!("{:?}", entity),
+                    );
+                }
+            });
+        ui.separator();
+
+        let Some((_, ai_meta)) = selection
+            .entity
+            .and_then(|entity| q_ai_meta.get(entity).ok())
+        else {
+            ui.label("Select an entity with an AI to inspect it");
+            return;
+        };
+
+        Grid::new("current_action").show(ui, |ui| {
+            ui.strong("Action");
+            ui.label(&ai_meta.current_action_name);
+            ui.end_row();
+            ui.strong("Score");
+            ui.label(format!("{:.2}", ai_meta.current_action_score));
+            ui.end_row();
+            ui.strong("Target");
+            ui.label(
+                ai_meta
+                    .current_target
+                    .map_or("None".into(), |target| format!("{:?}", target)),
+            );
+            ui.end_row();
+        });
+        ui.separator();
+
+        let Some(ai_definition) = ai_definitions.map.get(&ai_meta.ai_definition) else {
+            ui.label("The AI for this entity is not defined");
+            return;
+        };
+
+        for (idx, decision) in ai_definition.decisions.iter().enumerate() {
+            let is_current = Some(decision.action) == ai_meta.current_action;
+            let title = if is_current {
+                format!("{} (current)", decision.action_name)
+            } else {
+                decision.action_name.clone()
+            };
+            CollapsingHeader::new(title)
+                .id_source(idx)
+                .default_open(is_current)
+                .show(ui, |ui| decision_ui(ui, idx, decision, ai_meta));
+        }
+    });
+}
+
+/// Shows the raw input and score of each of the decision's considerations, targeted
+/// considerations are broken down per target or combination of targets.
+fn decision_ui(ui: &mut Ui, idx: usize, decision: &Decision, ai_meta: &AIMeta) {
+    ui.label(format!("Weight {:.2}", decision.weight));
+    if let Some(bucket) = &decision.bucket {
+        ui.label(format!("Bucket {}", bucket));
+    }
+
+    if !decision.simple_considerations.is_empty() {
+        considerations_grid(
+            ui,
+            (idx, "simple"),
+            decision.simple_considerations.iter().map(|consideration| {
+                let input = ai_meta.input_scores.get(&consideration.input).copied();
+                (consideration, input)
+            }),
+        );
+    }
+
+    if !decision.is_targeted {
+        return;
+    }
+
+    // each option is a single target, or a combination of targets for multi targeted decisions
+    let options: HashSet<Vec<Entity>> = decision
+        .targeted_considerations
+        .iter()
+        .filter_map(|consideration| ai_meta.targeted_input_scores.get(&consideration.input))
+        .flat_map(|score_map| score_map.keys().map(|&target| vec![target]))
+        .chain(
+            decision
+                .multi_targeted_considerations
+                .iter()
+                .filter_map(|consideration| {
+                    ai_meta
+                        .multi_targeted_input_scores
+                        .get(&consideration.input)
+                })
+                .flat_map(|score_map| score_map.keys().cloned()),
+        )
+        .collect();
+    let mut options: Vec<Vec<Entity>> = options.into_iter().collect();
+    options.sort();
+
+    if options.is_empty() {
+        ui.label("No targets");
+    }
+
+    for targets in options {
+        let is_current = Some(decision.action) == ai_meta.current_action
+            && targets == ai_meta.current_targets;
+        let title = match targets.as_slice() {
+            [target] => format!("Target {:?}", target),
+            targets => format!("Targets {:?}", targets),
+        };
+        let title = if is_current {
+            format!("{} (current)", title)
+        } else {
+            title
+        };
+        CollapsingHeader::new(title)
+            .id_source((idx, &targets))
+            .show(ui, |ui| {
+                let targeted_inputs =
+                    decision
+                        .targeted_considerations
+                        .iter()
+                        .map(|consideration| {
+                            let input = ai_meta
+                                .targeted_input_scores
+                                .get(&consideration.input)
+                                .and_then(|score_map| score_map.get(&targets[0]))
+                                .copied();
+                            (consideration, input)
+                        });
+                let multi_targeted_inputs =
+                    decision
+                        .multi_targeted_considerations
+                        .iter()
+                        .map(|consideration| {
+                            let input = ai_meta
+                                .multi_targeted_input_scores
+                                .get(&consideration.input)
+                                .and_then(|score_map| score_map.get(&targets))
+                                .copied();
+                            (consideration, input)
+                        });
+                considerations_grid(
+                    ui,
+                    (idx, &targets),
+                    targeted_inputs.chain(multi_targeted_inputs),
+                );
+            });
+    }
+}
+
+fn considerations_grid<'a>(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    considerations: impl Iterator<Item = (&'a Consideration, Option<f32>)>,
+) {
+    Grid::new(id_source).striped(true).show(ui, |ui| {
+        ui.strong("Input");
+        ui.strong("Raw");
+        ui.strong("Score");
+        ui.end_row();
+
+        for (consideration, input) in considerations {
+            ui.label(&consideration.input_name);
+            match input {
+                Some(input) if input != f32::NEG_INFINITY => {
+                    ui.label(format!("{:.2}", input));
+                    ui.label(format!("{:.2}", consideration.score(input)));
+                }
+                _ => {
+                    ui.label("-");
+                    ui.label("-");
+                }
+            }
+            ui.end_row();
+        }
+    });
+}
@@ -1,25 +1,106 @@
+pub mod action_factory;
 pub mod ai_meta;
 pub mod considerations;
 pub mod define_ai;
+pub mod definition_asset;
+pub mod events;
+pub mod history;
+pub mod input_id;
+#[cfg(feature = "inspector")]
+pub mod inspector;
 pub mod plugin;
+pub mod registry;
 pub mod response_curves;
+pub mod selection;
+pub mod spatial;
 pub mod systems;
-pub use bevy_utility_ai_macros::{input_system, targeted_input_system};
+pub use bevy_utility_ai_macros::{
+    input_system, multi_targeted_input_system, target_selector, targeted_input_system,
+};
 
+use crate::action_factory::ActionFactory;
 pub use crate::ai_meta::AIMeta;
 use crate::considerations::Consideration;
+pub use crate::input_id::InputId;
+use crate::selection::SelectionStrategy;
+use crate::spatial::{SpatialFilter, SpatialIndex};
+use serde::{Deserialize, Serialize};
 use std::any::TypeId;
+use std::time::Duration;
 
 use bevy::{
     prelude::{Component, Entity, Resource},
     utils::{HashMap, HashSet},
 };
 
+#[derive(Default, Clone)]
 pub struct AIDefinition {
     pub decisions: Vec<Decision>,
-    pub required_inputs: HashSet<usize>,
-    /// map of targeted_input_system key to set of target filter set keys, seeAITargetEntitySets
-    pub targeted_input_filter_sets: HashMap<usize, Vec<usize>>,
+    pub required_inputs: HashSet<InputId>,
+    /// map of targeted_input_system key to the target filter to apply, see AITargetEntitySets
+    pub targeted_input_filter_sets: HashMap<InputId, TargetFilter>,
+    /// map of multi_targeted_input_system key to the target filter to apply to each of its
+    /// targets, see Consideration::for_target
+    pub multi_targeted_input_filter_sets: HashMap<InputId, Vec<TargetFilter>>,
+    /// bonus applied to the score of the current action and target, see DefineAI::set_momentum
+    pub momentum: f32,
+    /// margin a new option must beat the current one by, see DefineAI::set_switching_margin
+    pub switching_margin: f32,
+    /// whether consideration scores are compensated, see DefineAI::set_compensate_considerations
+    pub compensate_considerations: bool,
+    /// how the AI picks between its evaluated decisions, see DefineAI::set_selection_strategy
+    pub selection_strategy: SelectionStrategy,
+    /// penalty applied to action and target pairs that failed, see DefineAI::set_failure_penalty
+    pub failure_penalty: FailurePenalty,
+    /// number of ticks between evaluations of each entity, see DefineAI::set_decision_interval
+    pub decision_interval: u32,
+    /// map of input key to the max age of its cached scores, see Consideration::with_cache
+    pub cached_inputs: HashMap<InputId, u32>,
+    /// the buckets decisions can be grouped into, see DefineAI::add_bucket
+    pub buckets: Vec<DecisionBucket>,
+}
+
+impl AIDefinition {
+    /// Returns true if the entity is scheduled to be evaluated on this tick. Entities are
+    /// staggered by their index so that only a slice of them is evaluated each tick.
+    pub fn is_scheduled(&self, entity: Entity, tick: u64) -> bool {
+        self.decision_interval <= 1
+            || (tick + entity.index() as u64).is_multiple_of(self.decision_interval as u64)
+    }
+
+    /// Returns the priority of the bucket the decision is in, decisions that aren't in a bucket
+    /// have a priority of 0.0.
+    pub fn bucket_priority(&self, decision: &Decision) -> f32 {
+        decision
+            .bucket
+            .as_ref()
+            .and_then(|name| self.buckets.iter().find(|bucket| &bucket.name == name))
+            .map_or(0.0, |bucket| bucket.priority)
+    }
+}
+
+/// Describes how the filter sets in AITargetEntitySets combine to form the set of valid targets.
+/// A target is valid if it is in any of the `include` sets, in all of the `require` sets and in
+/// none of the `exclude` sets. If there is a `spatial` filter the target must also be near the
+/// subject, and if there is a `selector` the target must have been selected by that target
+/// selector, see AITargetEntitySets::candidates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TargetFilter {
+    pub include: Vec<InputId>,
+    pub require: Vec<InputId>,
+    pub exclude: Vec<InputId>,
+    pub spatial: Option<SpatialFilter>,
+    pub selector: Option<InputId>,
+}
+
+impl TargetFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.require.is_empty()
+            && self.exclude.is_empty()
+            && self.spatial.is_none()
+            && self.selector.is_none()
+    }
 }
 
 #[derive(Resource, Default)]
@@ -27,14 +108,21 @@ pub struct AIDefinitions {
     pub map: HashMap<TypeId, AIDefinition>,
 }
 
+/// Counts the ticks the AI has run for, it is incremented once per frame before any inputs are
+/// calculated.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct AITick(pub u64);
+
 #[derive(Resource, Default)]
 pub struct AITargetEntitySets {
     // map of filter_system key to entity set
-    entity_set_map: HashMap<usize, HashSet<Entity>>,
+    entity_set_map: HashMap<InputId, HashSet<Entity>>,
+    // positions of entities for spatial filters, see AISpatialIndexAppExt
+    pub(crate) spatial_index: Option<SpatialIndex>,
 }
 
 impl AITargetEntitySets {
-    pub fn get(&self, entity_set: usize) -> Vec<Entity> {
+    pub fn get(&self, entity_set: InputId) -> Vec<Entity> {
         self.entity_set_map
             .get(&entity_set)
             .into_iter()
@@ -43,15 +131,122 @@ impl AITargetEntitySets {
             .collect()
     }
 
-    pub fn insert(&mut self, filter_system_key: usize, entity: Entity) {
+    pub fn insert(&mut self, filter_system_key: InputId, entity: Entity) {
         let entry = self.entity_set_map.entry(filter_system_key).or_default();
         entry.insert(entity);
     }
 
-    pub fn remove(&mut self, filter_system_key: usize, entity: Entity) {
+    pub fn remove(&mut self, filter_system_key: InputId, entity: Entity) {
         let entry = self.entity_set_map.entry(filter_system_key).or_default();
         entry.remove(&entity);
     }
+
+    pub fn contains(&self, filter_system_key: InputId, entity: Entity) -> bool {
+        self.entity_set_map
+            .get(&filter_system_key)
+            .is_some_and(|set| set.contains(&entity))
+    }
+
+    /// Returns the spatial index, if one has been added to the App.
+    pub fn spatial_index(&self) -> Option<&SpatialIndex> {
+        self.spatial_index.as_ref()
+    }
+
+    /// Returns the entities that pass the given filter for the subject. Returns None if the
+    /// filter has no `include` or `require` sets, no usable spatial filter and no selector, as
+    /// in that case every entity is a candidate and the caller should check each one with
+    /// `is_excluded`. Spatial filters are ignored if there is no spatial index or the subject
+    /// isn't in it. `selected_targets` are the targets the filter's selector chose for the
+    /// subject, see AIMeta::selected_targets.
+    pub fn candidates(
+        &self,
+        filter: &TargetFilter,
+        subject: Entity,
+        selected_targets: Option<&[Entity]>,
+    ) -> Option<Vec<Entity>> {
+        let selected: Option<HashSet<Entity>> = filter.selector.map(|_| {
+            selected_targets
+                .unwrap_or_default()
+                .iter()
+                .copied()
+                .collect()
+        });
+        let is_selected = |entity: Entity| {
+            selected
+                .as_ref()
+                .is_none_or(|selected| selected.contains(&entity))
+        };
+
+        let spatial_candidates = filter.spatial.zip(self.spatial_index.as_ref()).and_then(
+            |(spatial_filter, spatial_index)| {
+                spatial_index.candidates(subject, spatial_filter, |entity| {
+                    is_selected(entity) && self.passes_sets(filter, entity)
+                })
+            },
+        );
+        if spatial_candidates.is_some() {
+            return spatial_candidates;
+        }
+
+        if filter.include.is_empty() && filter.require.is_empty() {
+            return filter.selector.map(|_| {
+                selected_targets
+                    .unwrap_or_default()
+                    .iter()
+                    .copied()
+                    .filter(|&entity| !self.is_excluded(filter, entity))
+                    .collect()
+            });
+        }
+
+        let mut candidates: HashSet<Entity> = if filter.include.is_empty() {
+            self.entity_set_map
+                .get(&filter.require[0])
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            filter
+                .include
+                .iter()
+                .flat_map(|&set_key| self.entity_set_map.get(&set_key))
+                .flatten()
+                .cloned()
+                .collect()
+        };
+
+        candidates.retain(|&entity| {
+            filter
+                .require
+                .iter()
+                .all(|&set_key| self.contains(set_key, entity))
+                && !self.is_excluded(filter, entity)
+                && is_selected(entity)
+        });
+
+        Some(candidates.into_iter().collect())
+    }
+
+    /// Returns true if the entity passes the filter's `include`, `require` and `exclude` sets.
+    pub fn passes_sets(&self, filter: &TargetFilter, entity: Entity) -> bool {
+        (filter.include.is_empty()
+            || filter
+                .include
+                .iter()
+                .any(|&set_key| self.contains(set_key, entity)))
+            && filter
+                .require
+                .iter()
+                .all(|&set_key| self.contains(set_key, entity))
+            && !self.is_excluded(filter, entity)
+    }
+
+    /// Returns true if the entity is in any of the filter's `exclude` sets.
+    pub fn is_excluded(&self, filter: &TargetFilter, entity: Entity) -> bool {
+        filter
+            .exclude
+            .iter()
+            .any(|&set_key| self.contains(set_key, entity))
+    }
 }
 
 /// A component to hold the Target entity ID
@@ -60,11 +255,92 @@ pub struct ActionTarget {
     pub target: Entity,
 }
 
+/// A component to hold the chosen combination of targets for multi targeted actions, in the
+/// order of the input's targets. ActionTarget holds the first of these.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub struct ActionTargets {
+    pub targets: Vec<Entity>,
+}
+
+/// A component that action systems set to tell the framework how their action is going, it is
+/// added as Running whenever the entity starts a new action. Success and Failure cause the
+/// entity to make a new decision straight away, ignoring momentum and minimum commitment, and
+/// Failure also applies the AI's FailurePenalty to the action and target.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActionStatus {
+    #[default]
+    Running,
+    Success,
+    Failure,
+}
+
+/// A named group of decisions, the AI picks from the highest priority bucket that has a
+/// decision with a score above zero, see DefineAI::add_bucket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DecisionBucket {
+    pub name: String,
+    pub priority: f32,
+}
+
+/// Multiplies the score of an action and target pair that recently failed, see ActionStatus.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FailurePenalty {
+    pub multiplier: f32,
+    /// how long the penalty lasts for
+    pub duration: Commitment,
+}
+
+impl Default for FailurePenalty {
+    fn default() -> Self {
+        Self {
+            multiplier: 0.5,
+            duration: Commitment::Duration(Duration::from_secs(5)),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Decision {
     pub action_name: String,
     pub action: TypeId,
     pub simple_considerations: Vec<Consideration>,
     pub targeted_considerations: Vec<Consideration>,
     pub targeted_filter_considerations: Vec<Consideration>,
+    pub multi_targeted_considerations: Vec<Consideration>,
+    /// supplies the candidate targets of the decision, see Consideration::target_selector
+    pub target_selector: Option<Consideration>,
     pub is_targeted: bool,
+    /// overrides AIDefinition::momentum for this decision
+    pub momentum: Option<f32>,
+    /// multiplies the decision's score
+    pub weight: f32,
+    /// how long the action must be kept before the entity makes a new decision
+    pub min_commitment: Option<Commitment>,
+    /// overrides AIDefinition::compensate_considerations for this decision
+    pub compensate_considerations: Option<bool>,
+    /// builds the action component, otherwise it is created with its Default implementation
+    pub action_factory: Option<ActionFactory>,
+    /// the name of the bucket the decision is in, see AIDefinition::bucket_priority
+    pub bucket: Option<String>,
+}
+
+impl Decision {
+    /// Returns all of the decision's considerations, including its filters and target selector.
+    pub fn considerations(&self) -> impl Iterator<Item = &Consideration> {
+        self.simple_considerations
+            .iter()
+            .chain(&self.targeted_considerations)
+            .chain(&self.targeted_filter_considerations)
+            .chain(&self.multi_targeted_considerations)
+            .chain(&self.target_selector)
+    }
+}
+
+/// The minimum amount of time an action must be kept once chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Commitment {
+    /// The number of times the entity is evaluated, see DefineAI::set_decision_interval.
+    Ticks(u32),
+    /// The amount of elapsed Time.
+    Duration(Duration),
 }
@@ -1,12 +1,20 @@
 use crate::define_ai::AddedSystemTracker;
+use crate::events::{ActionEnded, ActionStarted, TargetChanged};
+use crate::registry::AIRegistry;
+use crate::selection::AIRng;
 use crate::{
-    systems::{make_decisions, update_action, UpdateEntityAction},
-    AIDefinitions, AITargetEntitySets,
+    systems::{make_decisions, schedule_decisions, update_action, UpdateEntityAction},
+    AIDefinitions, AITargetEntitySets, AITick,
 };
+use bevy::core::TaskPoolPlugin;
 use bevy::prelude::{IntoSystemConfig, IntoSystemSetConfig, Plugin, SystemSet};
+use bevy::time::TimePlugin;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum UtililityAISet {
+    UpdateTargetFilters,
+    ScheduleDecisions,
+    SelectTargets,
     CalculateInputs,
     MakeDecisions,
     UpdateActions,
@@ -16,12 +24,48 @@ pub struct UtilityAIPlugin;
 
 impl Plugin for UtilityAIPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
+        // the input systems and make_decisions use parallel queries, which need the
+        // ComputeTaskPool set up by the TaskPoolPlugin
+        if !app.is_plugin_added::<TaskPoolPlugin>() {
+            panic!(
+                "The UtilityAIPlugin requires the TaskPoolPlugin, which is part of the \
+                DefaultPlugins and MinimalPlugins!"
+            )
+        }
+
+        // Duration commitments and failure penalties would never expire without the TimePlugin
+        // advancing Time
+        if !app.is_plugin_added::<TimePlugin>() {
+            panic!(
+                "The UtilityAIPlugin requires the TimePlugin, which is part of the \
+                DefaultPlugins and MinimalPlugins!"
+            )
+        }
+
         app.add_event::<UpdateEntityAction>()
+            .add_event::<ActionStarted>()
+            .add_event::<ActionEnded>()
+            .add_event::<TargetChanged>()
             .init_resource::<AIDefinitions>()
             .init_resource::<AITargetEntitySets>()
             .init_resource::<AddedSystemTracker>()
+            .init_resource::<AIRng>()
+            .init_resource::<AIRegistry>()
+            .init_resource::<AITick>()
+            .add_system(schedule_decisions.in_set(UtililityAISet::ScheduleDecisions))
             .add_system(make_decisions.in_set(UtililityAISet::MakeDecisions))
             .add_system(update_action.in_set(UtililityAISet::UpdateActions))
+            .configure_set(
+                UtililityAISet::UpdateTargetFilters.before(UtililityAISet::CalculateInputs),
+            )
+            .configure_set(
+                UtililityAISet::ScheduleDecisions.before(UtililityAISet::CalculateInputs),
+            )
+            .configure_set(
+                UtililityAISet::SelectTargets
+                    .after(UtililityAISet::ScheduleDecisions)
+                    .before(UtililityAISet::CalculateInputs),
+            )
             .configure_set(
                 UtililityAISet::CalculateInputs.before(UtililityAISet::MakeDecisions),
             )
@@ -0,0 +1,188 @@
+use crate::considerations::{Consideration, TargetFilterType};
+use crate::define_ai::add_input_system;
+use crate::input_id::InputMarker;
+use crate::InputId;
+use bevy::app::{App, AppTypeRegistry, IntoSystemAppConfig};
+use bevy::prelude::{Component, Resource};
+use bevy::reflect::GetTypeRegistration;
+use bevy::utils::HashMap;
+use std::any::{type_name, TypeId};
+
+type ConsiderationFactory = Box<dyn Fn() -> Consideration + Send + Sync>;
+type FilterFactory = Box<dyn Fn(TargetFilterType) -> Consideration + Send + Sync>;
+
+/// An action that has been registered by name, see AIRegistry.
+#[derive(Clone, Debug)]
+pub struct RegisteredAction {
+    pub action: TypeId,
+    pub action_name: String,
+}
+
+/// A registry of inputs, filters and actions by name, this allows AIs to be described by data
+/// such as an AIDefinitionAsset. Populate it using the RegisterAIAppExt methods on the App.
+/// The names that the InputIds of all inputs added to the App were derived from, including
+/// those added by DefineAI, are also recorded so that InputIds can be shown by name.
+#[derive(Resource, Default)]
+pub struct AIRegistry {
+    inputs: HashMap<String, ConsiderationFactory>,
+    input_ids: HashMap<String, InputId>,
+    filters: HashMap<String, FilterFactory>,
+    actions: HashMap<String, RegisteredAction>,
+    input_names: HashMap<InputId, String>,
+}
+
+impl AIRegistry {
+    /// Returns a new Consideration for the input registered under this name.
+    pub fn input(&self, name: &str) -> Option<Consideration> {
+        self.inputs
+            .get(name)
+            .map(|factory| factory().set_input_name(name.into()))
+    }
+
+    /// Returns a new targeted filter Consideration for the component registered under this name.
+    pub fn filter(&self, name: &str, filter_type: TargetFilterType) -> Option<Consideration> {
+        self.filters.get(name).map(|factory| factory(filter_type))
+    }
+
+    /// Returns the action registered under this name.
+    pub fn action(&self, name: &str) -> Option<&RegisteredAction> {
+        self.actions.get(name)
+    }
+
+    /// Returns the InputId of the input registered under this name, the same name that
+    /// AIRegistry::input takes. Inputs that were only added by DefineAI aren't registered.
+    pub fn input_id(&self, name: &str) -> Option<InputId> {
+        self.input_ids.get(name).copied()
+    }
+
+    /// Returns the name that the InputId of an input added to the App was derived from, this is
+    /// the input's module path and function name, or the name given to its macro with
+    /// `#[input_system(name = "...")]`. It isn't the name the input is registered under.
+    pub fn input_name(&self, input_id: InputId) -> Option<&str> {
+        self.input_names.get(&input_id).map(String::as_str)
+    }
+
+    pub(crate) fn insert_input_name(&mut self, input_id: InputId, name: &str) {
+        let input_name = self
+            .input_names
+            .entry(input_id)
+            .or_insert_with(|| name.into());
+        if input_name != name {
+            panic!(
+                "The inputs '{}' and '{}' have the same InputId, give one of them a different \
+                name!",
+                input_name, name
+            )
+        }
+    }
+}
+
+/// Methods for registering inputs, filters and actions by name with the App.
+pub trait RegisterAIAppExt {
+    fn register_ai_input<M: InputMarker>(
+        &mut self,
+        name: &str,
+        input: impl IntoSystemAppConfig<M> + Copy + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    fn register_ai_targeted_input<M: InputMarker>(
+        &mut self,
+        name: &str,
+        input: impl IntoSystemAppConfig<M> + Copy + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    fn register_ai_multi_targeted_input<M: InputMarker>(
+        &mut self,
+        name: &str,
+        input: impl IntoSystemAppConfig<M> + Copy + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    fn register_ai_filter<F: Component>(&mut self, name: &str) -> &mut Self;
+
+    fn register_ai_action<C: Component + GetTypeRegistration>(
+        &mut self,
+        name: &str,
+    ) -> &mut Self;
+}
+
+impl RegisterAIAppExt for App {
+    fn register_ai_input<M: InputMarker>(
+        &mut self,
+        name: &str,
+        input: impl IntoSystemAppConfig<M> + Copy + Send + Sync + 'static,
+    ) -> &mut Self {
+        register_input(self, name, Box::new(move || Consideration::simple(input)))
+    }
+
+    fn register_ai_targeted_input<M: InputMarker>(
+        &mut self,
+        name: &str,
+        input: impl IntoSystemAppConfig<M> + Copy + Send + Sync + 'static,
+    ) -> &mut Self {
+        register_input(self, name, Box::new(move || Consideration::targeted(input)))
+    }
+
+    fn register_ai_multi_targeted_input<M: InputMarker>(
+        &mut self,
+        name: &str,
+        input: impl IntoSystemAppConfig<M> + Copy + Send + Sync + 'static,
+    ) -> &mut Self {
+        register_input(
+            self,
+            name,
+            Box::new(move || Consideration::multi_targeted(input)),
+        )
+    }
+
+    fn register_ai_filter<F: Component>(&mut self, name: &str) -> &mut Self {
+        let factory: FilterFactory = Box::new(|filter_type| match filter_type {
+            TargetFilterType::Include => Consideration::targeted_filter::<F>(),
+            TargetFilterType::Require => Consideration::targeted_require_filter::<F>(),
+            TargetFilterType::Exclude => Consideration::targeted_exclude_filter::<F>(),
+        });
+        add_input_system(self, &mut factory(TargetFilterType::Include));
+        registry_mut(self).filters.insert(name.into(), factory);
+        self
+    }
+
+    fn register_ai_action<C: Component + GetTypeRegistration>(
+        &mut self,
+        name: &str,
+    ) -> &mut Self {
+        self.world
+            .resource_mut::<AppTypeRegistry>()
+            .write()
+            .add_registration(C::get_type_registration());
+        registry_mut(self).actions.insert(
+            name.into(),
+            RegisteredAction {
+                action: TypeId::of::<C>(),
+                action_name: type_name::<C>().into(),
+            },
+        );
+        self
+    }
+}
+
+/// Registers the input and adds its system to the App, as AIs defined from data may require the
+/// input at any point.
+fn register_input<'a>(
+    app: &'a mut App,
+    name: &str,
+    factory: ConsiderationFactory,
+) -> &'a mut App {
+    let mut consideration = factory();
+    add_input_system(app, &mut consideration);
+    let mut registry = registry_mut(app);
+    registry.input_ids.insert(name.into(), consideration.input);
+    registry.inputs.insert(name.into(), factory);
+    app
+}
+
+fn registry_mut(app: &mut App) -> bevy::prelude::Mut<'_, AIRegistry> {
+    app.world
+        .get_resource_mut::<AIRegistry>()
+        .unwrap_or_else(|| {
+            panic!("Make sure the plugin is added to the app before registering AI inputs")
+        })
+}
@@ -4,6 +4,63 @@
 /// the transform to avoid unexpected capping by the framework.
 pub trait ResponseCurve: Send + Sync {
     fn transform(&self, input: f32) -> f32;
+
+    /// Returns a curve that implements `y = 1 - f(x)`
+    fn inverted(self) -> InvertedCurve<Self>
+    where
+        Self: Sized,
+    {
+        InvertedCurve::new(self)
+    }
+
+    /// Returns a curve whose output is clamped between min and max, which must be finite with
+    /// min no greater than max.
+    fn clamped(self, min: f32, max: f32) -> ClampedCurve<Self>
+    where
+        Self: Sized,
+    {
+        ClampedCurve::new(self, min, max)
+    }
+
+    /// Returns a curve that maps the input range min..max onto 0..1 before transforming it, the
+    /// max must be greater than the min.
+    fn remapped(self, min: f32, max: f32) -> RemappedCurve<Self>
+    where
+        Self: Sized,
+    {
+        RemappedCurve::new(self, min, max)
+    }
+
+    /// Returns a curve that implements `y = f(x) * g(x)`
+    fn multiplied_by<G: ResponseCurve>(self, other: G) -> ProductCurve<Self, G>
+    where
+        Self: Sized,
+    {
+        ProductCurve::new(self, other)
+    }
+
+    /// Returns a curve that implements `y = min(f(x), g(x))`
+    fn min_with<G: ResponseCurve>(self, other: G) -> MinCurve<Self, G>
+    where
+        Self: Sized,
+    {
+        MinCurve::new(self, other)
+    }
+
+    /// Returns a curve that implements `y = max(f(x), g(x))`
+    fn max_with<G: ResponseCurve>(self, other: G) -> MaxCurve<Self, G>
+    where
+        Self: Sized,
+    {
+        MaxCurve::new(self, other)
+    }
+}
+
+/// Allows curves built at runtime, such as from an AIDefinitionAsset, to be combined.
+impl ResponseCurve for Box<dyn ResponseCurve> {
+    fn transform(&self, input: f32) -> f32 {
+        self.as_ref().transform(input)
+    }
 }
 
 /// Implements the formula `y = slope * (x - x_shift) + y_shift`
@@ -101,3 +158,428 @@ impl ResponseCurve for LogisticCurve {
         1.0 / (1.0 + self.k.powf(-input + self.x_shift)) + self.y_shift
     }
 }
+
+/// Implements the formula `y = high if x >= threshold else low`
+pub struct StepCurve {
+    pub threshold: f32,
+    pub low: f32,
+    pub high: f32,
+}
+
+impl StepCurve {
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            low: 0.0,
+            high: 1.0,
+        }
+    }
+
+    pub fn with_values(self, low: f32, high: f32) -> Self {
+        Self {
+            threshold: self.threshold,
+            low,
+            high,
+        }
+    }
+}
+
+impl ResponseCurve for StepCurve {
+    fn transform(&self, input: f32) -> f32 {
+        if input >= self.threshold {
+            self.high
+        } else {
+            self.low
+        }
+    }
+}
+
+/// Implements the formula `y = k / (x - x_shift) + y_shift`
+pub struct InverseCurve {
+    pub k: f32,
+    pub x_shift: f32,
+    pub y_shift: f32,
+}
+
+impl InverseCurve {
+    pub fn new(k: f32) -> Self {
+        Self {
+            k,
+            x_shift: 0.0,
+            y_shift: 0.0,
+        }
+    }
+
+    pub fn shifted(self, x_shift: f32, y_shift: f32) -> Self {
+        Self {
+            k: self.k,
+            x_shift,
+            y_shift,
+        }
+    }
+}
+
+impl ResponseCurve for InverseCurve {
+    fn transform(&self, input: f32) -> f32 {
+        self.k / (input - self.x_shift) + self.y_shift
+    }
+}
+
+/// Implements the formula `y = e ^ (-k * (x - x_shift)) + y_shift`
+pub struct ExponentialDecayCurve {
+    pub k: f32,
+    pub x_shift: f32,
+    pub y_shift: f32,
+}
+
+impl ExponentialDecayCurve {
+    pub fn new(k: f32) -> Self {
+        Self {
+            k,
+            x_shift: 0.0,
+            y_shift: 0.0,
+        }
+    }
+
+    pub fn shifted(self, x_shift: f32, y_shift: f32) -> Self {
+        Self {
+            k: self.k,
+            x_shift,
+            y_shift,
+        }
+    }
+}
+
+impl ResponseCurve for ExponentialDecayCurve {
+    fn transform(&self, input: f32) -> f32 {
+        (-self.k * (input - self.x_shift)).exp() + self.y_shift
+    }
+}
+
+/// Implements the formula `y = amplitude * sin(frequency * (x - x_shift)) + y_shift`
+pub struct SineCurve {
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub x_shift: f32,
+    pub y_shift: f32,
+}
+
+impl SineCurve {
+    pub fn new(amplitude: f32, frequency: f32) -> Self {
+        Self {
+            amplitude,
+            frequency,
+            x_shift: 0.0,
+            y_shift: 0.0,
+        }
+    }
+
+    pub fn shifted(self, x_shift: f32, y_shift: f32) -> Self {
+        Self {
+            amplitude: self.amplitude,
+            frequency: self.frequency,
+            x_shift,
+            y_shift,
+        }
+    }
+}
+
+impl ResponseCurve for SineCurve {
+    fn transform(&self, input: f32) -> f32 {
+        self.amplitude * (self.frequency * (input - self.x_shift)).sin() + self.y_shift
+    }
+}
+
+/// Implements the formula `y = ln(x / (1 - x)) / (2 * k) + 0.5`, this maps an input in 0..1 onto
+/// 0..1 with the steepest change at the extremes. The output is in range for inputs whose logit
+/// is within +-k, a k of 5 is a good starting point. Inputs are clamped to 0..1.
+pub struct NormalisedLogitCurve {
+    pub k: f32,
+}
+
+impl NormalisedLogitCurve {
+    pub fn new(k: f32) -> Self {
+        Self { k }
+    }
+}
+
+impl ResponseCurve for NormalisedLogitCurve {
+    fn transform(&self, input: f32) -> f32 {
+        // the logit is undefined outside 0..1
+        let input = input.clamp(0.0, 1.0);
+        (input / (1.0 - input)).ln() / (2.0 * self.k) + 0.5
+    }
+}
+
+/// Linearly interpolates between the given (x, y) control points, inputs outside the range of
+/// the control points take the value of the nearest point and NaN takes the value of the first.
+pub struct PiecewiseLinearCurve {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl PiecewiseLinearCurve {
+    pub fn new(mut points: Vec<(f32, f32)>) -> Self {
+        if points.is_empty() {
+            panic!("A PiecewiseLinearCurve requires at least one control point!")
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { points }
+    }
+}
+
+impl ResponseCurve for PiecewiseLinearCurve {
+    fn transform(&self, input: f32) -> f32 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if input <= first.0 || input.is_nan() {
+            return first.1;
+        }
+        if input >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.iter().position(|p| p.0 > input).unwrap();
+        let (x0, y0) = self.points[upper - 1];
+        let (x1, y1) = self.points[upper];
+        y0 + (input - x0) * (y1 - y0) / (x1 - x0)
+    }
+}
+
+/// Implements the formula `y = 1 - f(x)`
+pub struct InvertedCurve<F: ResponseCurve> {
+    pub curve: F,
+}
+
+impl<F: ResponseCurve> InvertedCurve<F> {
+    pub fn new(curve: F) -> Self {
+        Self { curve }
+    }
+}
+
+impl<F: ResponseCurve> ResponseCurve for InvertedCurve<F> {
+    fn transform(&self, input: f32) -> f32 {
+        1.0 - self.curve.transform(input)
+    }
+}
+
+/// Clamps the output of the inner curve between min and max, min must not be greater than max.
+pub struct ClampedCurve<F: ResponseCurve> {
+    pub curve: F,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl<F: ResponseCurve> ClampedCurve<F> {
+    pub fn new(curve: F, min: f32, max: f32) -> Self {
+        if let Err(err) = validate_clamped_range(min, max) {
+            panic!("{}!", err)
+        }
+        Self { curve, min, max }
+    }
+}
+
+/// Returns an error if a ClampedCurve can't clamp between min and max.
+pub(crate) fn validate_clamped_range(min: f32, max: f32) -> Result<(), String> {
+    if !min.is_finite() || !max.is_finite() {
+        return Err("A clamped curve must have a finite min and max".into());
+    }
+    if min > max {
+        return Err("A clamped curve must have a min no greater than its max".into());
+    }
+    Ok(())
+}
+
+impl<F: ResponseCurve> ResponseCurve for ClampedCurve<F> {
+    fn transform(&self, input: f32) -> f32 {
+        self.curve.transform(input).clamp(self.min, self.max)
+    }
+}
+
+/// Maps the input range min..max onto 0..1 before passing it to the inner curve, this allows a
+/// curve authored for 0..1 to be reused for inputs with other ranges. The max must be greater
+/// than the min.
+pub struct RemappedCurve<F: ResponseCurve> {
+    pub curve: F,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl<F: ResponseCurve> RemappedCurve<F> {
+    pub fn new(curve: F, min: f32, max: f32) -> Self {
+        if let Err(err) = validate_remapped_range(min, max) {
+            panic!("{}!", err)
+        }
+        Self { curve, min, max }
+    }
+}
+
+/// Returns an error if a RemappedCurve can't map min..max onto 0..1.
+pub(crate) fn validate_remapped_range(min: f32, max: f32) -> Result<(), String> {
+    if !min.is_finite() || !max.is_finite() {
+        return Err("A remapped curve must have a finite min and max".into());
+    }
+    if max <= min {
+        return Err("A remapped curve must have a max greater than its min".into());
+    }
+    Ok(())
+}
+
+impl<F: ResponseCurve> ResponseCurve for RemappedCurve<F> {
+    fn transform(&self, input: f32) -> f32 {
+        self.curve
+            .transform((input - self.min) / (self.max - self.min))
+    }
+}
+
+/// Implements the formula `y = f(x) * g(x)`
+pub struct ProductCurve<F: ResponseCurve, G: ResponseCurve> {
+    pub f: F,
+    pub g: G,
+}
+
+impl<F: ResponseCurve, G: ResponseCurve> ProductCurve<F, G> {
+    pub fn new(f: F, g: G) -> Self {
+        Self { f, g }
+    }
+}
+
+impl<F: ResponseCurve, G: ResponseCurve> ResponseCurve for ProductCurve<F, G> {
+    fn transform(&self, input: f32) -> f32 {
+        self.f.transform(input) * self.g.transform(input)
+    }
+}
+
+/// Implements the formula `y = min(f(x), g(x))`
+pub struct MinCurve<F: ResponseCurve, G: ResponseCurve> {
+    pub f: F,
+    pub g: G,
+}
+
+impl<F: ResponseCurve, G: ResponseCurve> MinCurve<F, G> {
+    pub fn new(f: F, g: G) -> Self {
+        Self { f, g }
+    }
+}
+
+impl<F: ResponseCurve, G: ResponseCurve> ResponseCurve for MinCurve<F, G> {
+    fn transform(&self, input: f32) -> f32 {
+        self.f.transform(input).min(self.g.transform(input))
+    }
+}
+
+/// Implements the formula `y = max(f(x), g(x))`
+pub struct MaxCurve<F: ResponseCurve, G: ResponseCurve> {
+    pub f: F,
+    pub g: G,
+}
+
+impl<F: ResponseCurve, G: ResponseCurve> MaxCurve<F, G> {
+    pub fn new(f: F, g: G) -> Self {
+        Self { f, g }
+    }
+}
+
+impl<F: ResponseCurve, G: ResponseCurve> ResponseCurve for MaxCurve<F, G> {
+    fn transform(&self, input: f32) -> f32 {
+        self.f.transform(input).max(self.g.transform(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::response_curves::{
+        ExponentialDecayCurve, InverseCurve, LinearCurve, NormalisedLogitCurve,
+        PiecewiseLinearCurve, ResponseCurve, SineCurve, StepCurve,
+    };
+    use std::f32::consts::PI;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn step_curve() {
+        let curve = StepCurve::new(0.5).with_values(0.2, 0.8);
+        assert_close(curve.transform(0.49), 0.2);
+        assert_close(curve.transform(0.5), 0.8);
+    }
+
+    #[test]
+    fn inverse_curve() {
+        let curve = InverseCurve::new(1.0).shifted(-1.0, 0.0);
+        assert_close(curve.transform(0.0), 1.0);
+        assert_close(curve.transform(1.0), 0.5);
+    }
+
+    #[test]
+    fn exponential_decay_curve() {
+        let curve = ExponentialDecayCurve::new(2.0_f32.ln());
+        assert_close(curve.transform(0.0), 1.0);
+        assert_close(curve.transform(1.0), 0.5);
+    }
+
+    #[test]
+    fn sine_curve() {
+        let curve = SineCurve::new(0.5, PI).shifted(0.0, 0.5);
+        assert_close(curve.transform(0.0), 0.5);
+        assert_close(curve.transform(0.5), 1.0);
+    }
+
+    #[test]
+    fn normalised_logit_curve() {
+        let curve = NormalisedLogitCurve::new(5.0);
+        assert_close(curve.transform(0.5), 0.5);
+        assert!(curve.transform(0.9) > 0.5);
+        assert!(curve.transform(0.1) < 0.5);
+        assert_eq!(curve.transform(1.5), f32::INFINITY);
+        assert_eq!(curve.transform(-0.5), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn piecewise_linear_curve() {
+        let curve = PiecewiseLinearCurve::new(vec![(1.0, 0.0), (0.0, 1.0), (0.5, 0.0)]);
+        assert_close(curve.transform(-1.0), 1.0);
+        assert_close(curve.transform(0.25), 0.5);
+        assert_close(curve.transform(0.75), 0.0);
+        assert_close(curve.transform(2.0), 0.0);
+        assert_close(curve.transform(f32::NAN), 1.0);
+    }
+
+    #[test]
+    fn combinators() {
+        let curve = LinearCurve::new(1.0).remapped(0.0, 100.0).inverted();
+        assert_close(curve.transform(25.0), 0.75);
+
+        let curve = LinearCurve::new(2.0).clamped(0.0, 1.0);
+        assert_close(curve.transform(0.75), 1.0);
+
+        let curve = LinearCurve::new(1.0).multiplied_by(StepCurve::new(0.5));
+        assert_close(curve.transform(0.25), 0.0);
+        assert_close(curve.transform(0.75), 0.75);
+
+        let curve = LinearCurve::new(1.0).min_with(LinearCurve::new(-1.0).shifted(0.0, 1.0));
+        assert_close(curve.transform(0.25), 0.25);
+        assert_close(curve.transform(0.75), 0.25);
+
+        let curve = LinearCurve::new(1.0).max_with(LinearCurve::new(-1.0).shifted(0.0, 1.0));
+        assert_close(curve.transform(0.25), 0.75);
+        assert_close(curve.transform(0.75), 0.75);
+    }
+
+    #[test]
+    #[should_panic(expected = "A clamped curve must have a min no greater than its max!")]
+    fn clamped_curve_min_must_not_exceed_max() {
+        LinearCurve::new(1.0).clamped(1.0, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "A clamped curve must have a finite min and max!")]
+    fn clamped_curve_bounds_must_be_finite() {
+        LinearCurve::new(1.0).clamped(f32::NAN, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "A remapped curve must have a max greater than its min!")]
+    fn remapped_curve_max_must_exceed_min() {
+        LinearCurve::new(1.0).remapped(1.0, 1.0);
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::{Entity, Resource};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// How an AI picks between its evaluated decisions, set with DefineAI::set_selection_strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SelectionStrategy {
+    /// Picks the highest scoring option. Ties are broken by the order the decisions were added
+    /// and then by the target entity, so the result is deterministic.
+    #[default]
+    Highest,
+    /// Picks randomly between the N highest scoring options, weighted by their score.
+    WeightedRandomTopN(usize),
+    /// Picks randomly between the options that score within the given fraction of the best
+    /// option, e.g. 0.1 for within 10%, weighted by their score.
+    WeightedRandomWithin(f32),
+}
+
+/// The random number generator used by the framework, insert it with a seed to make AI
+/// decisions reproducible between runs.
+#[derive(Resource)]
+pub struct AIRng(pub ChaCha8Rng);
+
+impl AIRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Returns a generator for a single entity, each entity uses its own stream so that
+    /// entities can make their decisions in parallel and still be reproducible.
+    pub(crate) fn for_entity(seed: u64, entity: Entity) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(entity.to_bits());
+        Self(rng)
+    }
+}
+
+impl Default for AIRng {
+    fn default() -> Self {
+        Self(ChaCha8Rng::from_entropy())
+    }
+}
+
+impl SelectionStrategy {
+    /// Returns the index of the chosen option given a list of scores sorted from best to worst.
+    pub(crate) fn select(&self, sorted_scores: &[f32], rng: &mut AIRng) -> usize {
+        let candidate_count = match *self {
+            SelectionStrategy::Highest => return 0,
+            SelectionStrategy::WeightedRandomTopN(n) => n.max(1).min(sorted_scores.len()),
+            SelectionStrategy::WeightedRandomWithin(fraction) => {
+                let threshold = sorted_scores[0] * (1.0 - fraction);
+                sorted_scores
+                    .iter()
+                    .take_while(|&&score| score >= threshold)
+                    .count()
+                    .max(1)
+            }
+        };
+
+        let candidates = &sorted_scores[..candidate_count];
+        let total: f32 = candidates.iter().map(|score| score.max(0.0)).sum();
+        if total <= 0.0 {
+            return 0;
+        }
+
+        let mut roll = rng.0.gen_range(0.0..total);
+        for (idx, score) in candidates.iter().enumerate() {
+            roll -= score.max(0.0);
+            if roll < 0.0 {
+                return idx;
+            }
+        }
+        candidate_count - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::selection::{AIRng, SelectionStrategy};
+
+    #[test]
+    fn highest_picks_first() {
+        let mut rng = AIRng::seeded(0);
+        assert_eq!(
+            SelectionStrategy::Highest.select(&[0.9, 0.9, 0.1], &mut rng),
+            0
+        );
+    }
+
+    #[test]
+    fn weighted_random_top_n_only_picks_top_n() {
+        let mut rng = AIRng::seeded(0);
+        for _ in 0..100 {
+            let idx =
+                SelectionStrategy::WeightedRandomTopN(2).select(&[0.9, 0.8, 0.7], &mut rng);
+            assert!(idx < 2);
+        }
+    }
+
+    #[test]
+    fn weighted_random_within_only_picks_close_scores() {
+        let mut rng = AIRng::seeded(0);
+        for _ in 0..100 {
+            let idx = SelectionStrategy::WeightedRandomWithin(0.1)
+                .select(&[1.0, 0.95, 0.5], &mut rng);
+            assert!(idx < 2);
+        }
+    }
+
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        let scores = [0.5, 0.5, 0.5, 0.5];
+        let picks = |seed| {
+            let mut rng = AIRng::seeded(seed);
+            (0..16)
+                .map(|_| SelectionStrategy::WeightedRandomTopN(4).select(&scores, &mut rng))
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(picks(42), picks(42));
+    }
+}
@@ -0,0 +1,297 @@
+use crate::plugin::UtililityAISet;
+use crate::AITargetEntitySets;
+use bevy::app::App;
+use bevy::prelude::{Component, Entity, IVec3, IntoSystemConfig, Query, ResMut, Vec3};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Implemented for the component that holds an entity's position, this is what the spatial
+/// index is built from, see AISpatialIndexAppExt.
+pub trait AIPosition: Component {
+    fn ai_position(&self) -> Vec3;
+}
+
+/// Restricts the targets of a targeted consideration to those near the subject, see
+/// Consideration::with_max_distance and Consideration::with_nearest_targets.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SpatialFilter {
+    /// Only targets within this distance of the subject.
+    WithinDistance(f32),
+    /// Only the N targets closest to the subject.
+    Nearest(usize),
+}
+
+/// A uniform grid of entity positions, rebuilt every tick from the AIPosition component.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<(Entity, Vec3)>>,
+    positions: HashMap<Entity, Vec3>,
+}
+
+impl SpatialIndex {
+    /// Creates an empty index, the cell size should be around the distance of a typical query.
+    pub fn new(cell_size: f32) -> Self {
+        if cell_size <= 0.0 {
+            panic!("The cell size of a spatial index must be positive!")
+        }
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            positions: HashMap::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.positions.clear();
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec3) {
+        self.cells
+            .entry(self.cell_of(position))
+            .or_default()
+            .push((entity, position));
+        self.positions.insert(entity, position);
+    }
+
+    /// Returns the position the entity was indexed at.
+    pub fn position(&self, entity: Entity) -> Option<Vec3> {
+        self.positions.get(&entity).copied()
+    }
+
+    /// Returns the entities passing the filter around the subject, or None if the subject isn't
+    /// in the index. The subject itself is never returned.
+    pub fn candidates(
+        &self,
+        subject: Entity,
+        filter: SpatialFilter,
+        mut predicate: impl FnMut(Entity) -> bool,
+    ) -> Option<Vec<Entity>> {
+        let center = self.position(subject)?;
+        let predicate = |entity: Entity| entity != subject && predicate(entity);
+        Some(match filter {
+            SpatialFilter::WithinDistance(distance) => {
+                self.within_distance(center, distance, predicate)
+            }
+            SpatialFilter::Nearest(count) => self.nearest(center, count, predicate),
+        })
+    }
+
+    /// Returns the entities within the distance of the center that match the predicate.
+    pub fn within_distance(
+        &self,
+        center: Vec3,
+        distance: f32,
+        mut predicate: impl FnMut(Entity) -> bool,
+    ) -> Vec<Entity> {
+        let mut found = Vec::new();
+        let mut visit = |entries: &Vec<(Entity, Vec3)>| {
+            for &(entity, position) in entries {
+                if position.distance_squared(center) <= distance * distance
+                    && predicate(entity)
+                {
+                    found.push(entity);
+                }
+            }
+        };
+
+        let min = self.cell_of(center - Vec3::splat(distance));
+        let max = self.cell_of(center + Vec3::splat(distance));
+        let cell_count = (0..3)
+            .map(|axis| (max[axis] as i64 - min[axis] as i64 + 1) as u64)
+            .fold(1u64, |count, span| count.saturating_mul(span));
+
+        // for large distances it is cheaper to check every occupied cell
+        if cell_count > self.cells.len() as u64 {
+            self.cells.values().for_each(&mut visit);
+        } else {
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        if let Some(entries) = self.cells.get(&IVec3::new(x, y, z)) {
+                            visit(entries);
+                        }
+                    }
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Returns up to `count` entities that match the predicate, from closest to furthest from
+    /// the center.
+    pub fn nearest(
+        &self,
+        center: Vec3,
+        count: usize,
+        mut predicate: impl FnMut(Entity) -> bool,
+    ) -> Vec<Entity> {
+        let mut found: Vec<(f32, Entity)> = Vec::new();
+        if count == 0 {
+            return Vec::new();
+        }
+
+        // search rings of cells outwards from the center, every entity outside of ring r is at
+        // least r cells away so the search can stop once the closest `count` are nearer
+        let origin = self.cell_of(center);
+        let mut visited = 0;
+        let mut ring: i32 = 0;
+        while visited < self.positions.len() {
+            let ring_cell_count = if ring == 0 {
+                1
+            } else {
+                (2 * ring as u64 + 1).pow(3) - (2 * ring as u64 - 1).pow(3)
+            };
+
+            // once the rings are larger than the occupied cells, check the remaining directly
+            if ring_cell_count > self.cells.len() as u64 {
+                found.clear();
+                for entries in self.cells.values() {
+                    for &(entity, position) in entries {
+                        if predicate(entity) {
+                            found.push((position.distance_squared(center), entity));
+                        }
+                    }
+                }
+                found.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                break;
+            }
+
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    // only the faces of the ring's cube are new cells
+                    let on_face = dx.abs() == ring || dy.abs() == ring;
+                    let step = if on_face { 1 } else { 2 * ring };
+                    let mut dz = -ring;
+                    while dz <= ring {
+                        if let Some(entries) =
+                            self.cells.get(&(origin + IVec3::new(dx, dy, dz)))
+                        {
+                            visited += entries.len();
+                            for &(entity, position) in entries {
+                                if predicate(entity) {
+                                    found.push((position.distance_squared(center), entity));
+                                }
+                            }
+                        }
+                        dz += step;
+                    }
+                }
+            }
+
+            found.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            let searched_distance = ring as f32 * self.cell_size;
+            if found.len() >= count
+                && found[count - 1].0 <= searched_distance * searched_distance
+            {
+                break;
+            }
+            ring += 1;
+        }
+
+        found.truncate(count);
+        found.into_iter().map(|(_, entity)| entity).collect()
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+}
+
+pub trait AISpatialIndexAppExt {
+    /// Indexes the position of every entity with the component P, this allows targeted
+    /// considerations to be restricted to nearby targets with a SpatialFilter. Only one spatial
+    /// index is supported per App.
+    fn add_ai_spatial_index<P: AIPosition>(&mut self, cell_size: f32) -> &mut Self;
+}
+
+impl AISpatialIndexAppExt for App {
+    fn add_ai_spatial_index<P: AIPosition>(&mut self, cell_size: f32) -> &mut Self {
+        let mut target_entity_sets = self
+            .world
+            .get_resource_mut::<AITargetEntitySets>()
+            .unwrap_or_else(|| {
+                panic!("Make sure the plugin is added to the app before the spatial index")
+            });
+        if target_entity_sets.spatial_index.is_some() {
+            panic!("A spatial index has already been added to the app!")
+        }
+        target_entity_sets.spatial_index = Some(SpatialIndex::new(cell_size));
+
+        self.add_system(update_spatial_index::<P>.in_set(UtililityAISet::UpdateTargetFilters))
+    }
+}
+
+/// Rebuilds the spatial index from the current positions.
+fn update_spatial_index<P: AIPosition>(
+    q_position: Query<(Entity, &P)>,
+    mut res_target_entity_sets: ResMut<AITargetEntitySets>,
+) {
+    let Some(spatial_index) = res_target_entity_sets.spatial_index.as_mut() else {
+        return;
+    };
+    spatial_index.clear();
+    for (entity, position) in q_position.iter() {
+        spatial_index.insert(entity, position.ai_position());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spatial::SpatialIndex;
+    use bevy::prelude::{Entity, Vec3};
+
+    fn index() -> (SpatialIndex, Vec<(Entity, Vec3)>) {
+        let mut index = SpatialIndex::new(10.0);
+        let entries: Vec<(Entity, Vec3)> = (0..200)
+            .map(|i| {
+                let i = i as f32;
+                let position = Vec3::new((i * 7.3) % 95.0, (i * 3.1) % 41.0, (i * 1.7) % 23.0);
+                (Entity::from_raw(i as u32), position - Vec3::splat(20.0))
+            })
+            .collect();
+        for &(entity, position) in &entries {
+            index.insert(entity, position);
+        }
+        (index, entries)
+    }
+
+    #[test]
+    fn within_distance_matches_brute_force() {
+        let (index, entries) = index();
+        for distance in [0.0, 5.0, 12.5, 40.0, 1000.0] {
+            let mut found = index.within_distance(Vec3::ZERO, distance, |_| true);
+            found.sort();
+            let mut expected: Vec<Entity> = entries
+                .iter()
+                .filter(|(_, position)| position.length() <= distance)
+                .map(|&(entity, _)| entity)
+                .collect();
+            expected.sort();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let (index, entries) = index();
+        let center = Vec3::new(3.0, -4.0, 1.0);
+        for count in [0, 1, 5, 50, 500] {
+            let found = index.nearest(center, count, |entity| entity.index() % 3 != 0);
+            let mut expected: Vec<(f32, Entity)> = entries
+                .iter()
+                .filter(|(entity, _)| entity.index() % 3 != 0)
+                .map(|&(entity, position)| (position.distance_squared(center), entity))
+                .collect();
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            let expected: Vec<Entity> = expected
+                .into_iter()
+                .take(count)
+                .map(|(_, entity)| entity)
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+}
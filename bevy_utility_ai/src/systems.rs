@@ -1,50 +1,132 @@
 mod make_decisions;
+mod schedule_decisions;
 mod update_action;
 pub(crate) use make_decisions::make_decisions;
+pub(crate) use schedule_decisions::schedule_decisions;
 pub(crate) use update_action::update_action;
 
-use std::any::TypeId;
+use std::any::{type_name, TypeId};
 
-use bevy::prelude::{Added, Commands, Component, Entity, Query, ResMut};
+use bevy::prelude::{
+    debug, Added, AppTypeRegistry, Commands, Component, Entity, Query, ReflectComponent,
+    RemovedComponents, ResMut, World,
+};
 
+use crate::action_factory::ActionFactory;
 use crate::ai_meta::AIMeta;
-use crate::AITargetEntitySets;
+use crate::events::ActionEnded;
+use crate::{AITargetEntitySets, ActionStatus, ActionTarget, ActionTargets, InputId};
 
 pub struct UpdateEntityAction {
     entity_id: Entity,
     old_action: Option<TypeId>,
     new_action: TypeId,
+    old_action_name: String,
+    new_action_name: String,
     old_target: Option<Entity>,
     new_target: Option<Entity>,
+    old_targets: Vec<Entity>,
+    new_targets: Vec<Entity>,
+    old_score: f32,
+    new_score: f32,
+    action_factory: Option<ActionFactory>,
 }
 
-// TODO: add system that watches for component removal
+/// Keeps the filter set for component `F` in sync, entities are added to the set when `F` is
+/// added and removed from it when `F` is removed or the entity is despawned.
 pub fn inclusive_filter_input<F: Component>(
     q_added: Query<Entity, Added<F>>,
+    mut removed: RemovedComponents<F>,
     mut res_target_filter_sets: ResMut<AITargetEntitySets>,
 ) {
-    let key = inclusive_filter_input::<F> as usize;
+    let key = InputId::filter::<F>();
+    for removed_entity in removed.iter() {
+        res_target_filter_sets.remove(key, removed_entity);
+    }
     for added_entity in q_added.iter() {
         res_target_filter_sets.insert(key, added_entity);
     }
 }
 
-// TODO: add system that watches for component removal
+/// Gives entities with the marker component T the AIMeta of its AI, and removes it again when T
+/// is removed. If the entity already has the AIMeta of another AI, e.g. because its marker
+/// component was swapped, its action is ended and the AIMeta is replaced so that none of the
+/// other AI's scores or failures carry over.
 pub(crate) fn ensure_entity_has_ai_meta<T: Component>(
     mut commmads: Commands,
-    query: Query<(Entity, Option<&AIMeta>), Added<T>>,
+    query: Query<Entity, Added<T>>,
+    mut removed: RemovedComponents<T>,
 ) {
-    for (entity, ai_meta) in &query {
-        if ai_meta.is_none() {
-            commmads.entity(entity).insert(AIMeta::new::<T>());
-        }
+    // the AIMeta is checked when the commands are applied, so that swapping one marker for
+    // another within a frame works whichever order their systems run in
+    for entity in &query {
+        commmads.add(move |world: &mut World| {
+            let Some(ai_meta) = world.get_entity(entity).map(|e| e.get::<AIMeta>()) else {
+                return;
+            };
+            match ai_meta {
+                Some(ai_meta) if ai_meta.ai_definition == TypeId::of::<T>() => {}
+                Some(_) => {
+                    end_current_action(world, entity);
+                    world.entity_mut(entity).insert(AIMeta::new::<T>());
+                    debug!("Swapped AI of {:?} to {}", entity, type_name::<T>());
+                }
+                None => {
+                    world.entity_mut(entity).insert(AIMeta::new::<T>());
+                }
+            }
+        });
+    }
+
+    for entity in removed.iter() {
+        commmads.add(move |world: &mut World| {
+            let is_ai = world
+                .get_entity(entity)
+                .and_then(|e| e.get::<AIMeta>())
+                .is_some_and(|ai_meta| ai_meta.ai_definition == TypeId::of::<T>());
+            if is_ai {
+                end_current_action(world, entity);
+                world.entity_mut(entity).remove::<AIMeta>();
+                debug!("Removed AI {} from {:?}", type_name::<T>(), entity);
+            }
+        });
+    }
+}
+
+/// Removes the entity's current action along with its target and status, and sends the
+/// ActionEnded event.
+fn end_current_action(world: &mut World, entity: Entity) {
+    let ai_meta = world.get::<AIMeta>(entity).unwrap();
+    let Some(action) = ai_meta.current_action else {
+        return;
+    };
+    let action_ended = ActionEnded {
+        entity,
+        action,
+        action_name: ai_meta.current_action_name.clone(),
+        target: ai_meta.current_target,
+        targets: ai_meta.current_targets.clone(),
+        score: ai_meta.current_action_score,
+    };
+
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let mut entity_mut = world.entity_mut(entity);
+    if let Some(reflect_component) = type_registry
+        .read()
+        .get(action)
+        .and_then(|registration| registration.data::<ReflectComponent>())
+    {
+        reflect_component.remove(&mut entity_mut);
     }
+    entity_mut.remove::<(ActionTarget, ActionTargets, ActionStatus)>();
+
+    world.send_event(action_ended);
 }
 
 #[cfg(test)]
 mod tests {
     use crate::systems::inclusive_filter_input;
-    use crate::{AIMeta, AITargetEntitySets};
+    use crate::{AIMeta, AITargetEntitySets, InputId};
     use bevy::app::App;
     use bevy::prelude::Component;
 
@@ -73,11 +155,44 @@ mod tests {
         let ai_target_entity_sets = app.world.get_resource::<AITargetEntitySets>().unwrap();
         let entity_set = ai_target_entity_sets
             .entity_set_map
-            .get(&(inclusive_filter_input::<FilterTarget> as usize))
+            .get(&InputId::filter::<FilterTarget>())
             .unwrap();
 
         assert!(!entity_set.is_empty());
         assert!(entity_set.contains(&entity_valid_target));
         assert!(!entity_set.contains(&entity_invalid_target));
     }
+
+    #[test]
+    fn inclusive_filter_input_removes_stale_targets() {
+        #[derive(Component)]
+        struct FilterTarget {}
+
+        let mut app = App::new();
+        app.init_resource::<AITargetEntitySets>();
+
+        app.add_system(inclusive_filter_input::<FilterTarget>);
+
+        let entity_removed = app.world.spawn((FilterTarget {},)).id();
+        let entity_despawned = app.world.spawn((FilterTarget {},)).id();
+        let entity_kept = app.world.spawn((FilterTarget {},)).id();
+
+        app.update();
+
+        app.world
+            .entity_mut(entity_removed)
+            .remove::<FilterTarget>();
+        app.world.despawn(entity_despawned);
+
+        app.update();
+
+        let ai_target_entity_sets = app.world.get_resource::<AITargetEntitySets>().unwrap();
+        let entity_set = ai_target_entity_sets
+            .entity_set_map
+            .get(&InputId::filter::<FilterTarget>())
+            .unwrap();
+
+        assert_eq!(entity_set.len(), 1);
+        assert!(entity_set.contains(&entity_kept));
+    }
 }
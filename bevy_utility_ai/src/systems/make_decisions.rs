@@ -1,20 +1,131 @@
+use crate::ai_meta::ActionFailure;
+use crate::history::{AIDecisionHistory, DecisionRecord, DecisionScore, SwitchReason};
+use crate::selection::AIRng;
 use crate::systems::UpdateEntityAction;
-use crate::{AIDefinitions, AIMeta, Decision};
+use crate::{AIDefinitions, AIMeta, AITick, ActionStatus, Commitment, Decision};
 use bevy::log::{debug, debug_span};
-use bevy::prelude::{Entity, EventWriter, Query, Res};
+use bevy::prelude::{Entity, EventWriter, Query, Res, ResMut};
+use bevy::time::Time;
 use bevy::utils::HashMap;
+use rand::Rng;
+use std::sync::Mutex;
 
 pub(crate) fn make_decisions(
-    mut query: Query<(Entity, &mut AIMeta)>,
+    mut query: Query<(
+        Entity,
+        &mut AIMeta,
+        Option<&mut AIDecisionHistory>,
+        Option<&mut ActionStatus>,
+    )>,
     mut event_writer: EventWriter<UpdateEntityAction>,
     ai_definitions: Res<AIDefinitions>,
+    time: Res<Time>,
+    mut rng: ResMut<AIRng>,
+    tick: Res<AITick>,
 ) {
     let _span = debug_span!("Making Decisions").entered();
 
-    for (entity_id, mut ai_meta) in query.iter_mut() {
-        let ai_definition = &ai_definitions.map[&ai_meta.ai_definition];
+    // each entity picks with its own generator, seeded from AIRng once per run, so that the
+    // decisions don't depend on the order the entities are evaluated in
+    let seed: u64 = rng.0.gen();
+    let update_entity_actions = Mutex::new(Vec::new());
 
+    // entities are evaluated independently, so they are spread across the ComputeTaskPool
+    query.par_iter_mut().for_each_mut(|(entity_id, mut ai_meta, history, action_status)| {
         let _span = debug_span!("", entity = entity_id.index()).entered();
+
+        // the AI may not be defined yet if it is loaded from an asset
+        let Some(ai_definition) = ai_definitions.map.get(&ai_meta.ai_definition) else {
+            debug!("skipped as the AI is not defined");
+            return;
+        };
+
+        if !ai_meta.is_due {
+            debug!("skipped as not due for evaluation");
+            return;
+        }
+        ai_meta.evaluations += 1;
+
+        // an action that succeeded or failed is re-decided straight away, the status is reset
+        // so that it is only handled once
+        let mut action_finished = false;
+        if let Some(mut action_status) = action_status {
+            if *action_status != ActionStatus::Running {
+                debug!(
+                    "Action '{}' finished with {:?}",
+                    ai_meta.current_action_name, *action_status
+                );
+                if *action_status == ActionStatus::Failure {
+                    if let Some(action) = ai_meta.current_action {
+                        let target = ai_meta.current_target;
+                        let targets = ai_meta.current_targets.clone();
+                        let evaluation = ai_meta.evaluations;
+                        ai_meta.action_failures.push(ActionFailure {
+                            action,
+                            target,
+                            targets,
+                            evaluation,
+                            time: time.elapsed(),
+                        });
+                    }
+                }
+                *action_status = ActionStatus::Running;
+                action_finished = true;
+            }
+        }
+
+        // forget failures once their penalty has expired, ticks count the entity's evaluations
+        // rather than AITicks so that they match min_commitment with a decision interval
+        let failure_penalty = ai_definition.failure_penalty;
+        if !ai_meta.action_failures.is_empty() {
+            let evaluations = ai_meta.evaluations;
+            ai_meta
+                .action_failures
+                .retain(|failure| match failure_penalty.duration {
+                    Commitment::Ticks(ticks) => evaluations - failure.evaluation < ticks as u64,
+                    Commitment::Duration(duration) => {
+                        time.elapsed().saturating_sub(failure.time) < duration
+                    }
+                });
+        }
+
+        // keep the current action until its minimum commitment has been met
+        ai_meta.current_action_ticks += 1;
+        let min_commitment = ai_meta
+            .current_decision
+            .and_then(|idx| ai_definition.decisions.get(idx))
+            .filter(|decision| Some(decision.action) == ai_meta.current_action)
+            .and_then(|decision| decision.min_commitment);
+        let is_committed = match min_commitment {
+            Some(Commitment::Ticks(ticks)) => ai_meta.current_action_ticks < ticks,
+            Some(Commitment::Duration(duration)) => {
+                time.elapsed()
+                    .saturating_sub(ai_meta.current_action_started)
+                    < duration
+            }
+            None => false,
+        };
+        if is_committed && !action_finished {
+            debug!(
+                "Committed to action '{}', skipping",
+                ai_meta.current_action_name
+            );
+            return;
+        }
+
+        // penalise action and target pairs that failed recently
+        let penalty = |decision: &Decision, targets: &[Entity]| {
+            let has_failed = ai_meta
+                .action_failures
+                .iter()
+                .any(|failure| failure.action == decision.action && failure.targets == targets);
+            if has_failed {
+                failure_penalty.multiplier
+            } else {
+                1.0
+            }
+        };
+
         let mut evaluated_decisions = Vec::new();
 
         for (idx, decision) in ai_definition.decisions.iter().enumerate() {
@@ -25,6 +136,20 @@ pub(crate) fn make_decisions(
 
             let mut decision_score = 1.0;
 
+            let consideration_count = decision.simple_considerations.len()
+                + decision.targeted_considerations.len()
+                + decision.multi_targeted_considerations.len();
+            let compensate = decision
+                .compensate_considerations
+                .unwrap_or(ai_definition.compensate_considerations);
+            let compensated = |score: f32| {
+                if compensate {
+                    compensate_score(score, consideration_count)
+                } else {
+                    score
+                }
+            };
+
             // consider non-targeted considerations
             for consideration in &decision.simple_considerations {
                 let consideration_input_score = *ai_meta
@@ -38,80 +163,209 @@ pub(crate) fn make_decisions(
                         consideration.input_name
                     );
                 } else {
-                    let consideration_score = consideration
-                        .response_curve
-                        .transform(consideration_input_score)
-                        .clamp(0.0, 1.0);
+                    let consideration_score = consideration.score(consideration_input_score);
                     debug!(
                         "Consideration score for {} is {:.2} (raw {:.2})",
                         consideration.input_name,
                         consideration_score,
                         consideration_input_score
                     );
-                    decision_score *= consideration_score;
+                    decision_score *= compensated(consideration_score);
                 }
             }
 
             if !decision.is_targeted {
-                evaluated_decisions.push((idx, None, decision_score));
+                decision_score *= decision.weight * penalty(decision, &[]);
+                evaluated_decisions.push((idx, Vec::new(), decision_score));
                 debug!("Decision {} scored {:.2}", idx, decision_score);
                 continue;
             }
 
-            let mut targeted_scores = HashMap::new();
+            // the decision score of each target, or set of targets for multi targeted
+            // considerations, along with the number of considerations that scored it
+            let mut targeted_scores: HashMap<Vec<Entity>, (f32, usize)> = HashMap::new();
 
             // consider targeted considerations
             for consideration in &decision.targeted_considerations {
-                let score_map = ai_meta.targeted_input_scores.get(&consideration.input);
-                if score_map.is_none() {
+                let Some(score_map) = ai_meta.targeted_input_scores.get(&consideration.input)
+                else {
                     debug!(
                         "No scores where registered for targeted input system {}",
                         consideration.input_name
                     );
+                    continue;
                 };
-                for (&target_entity, &consideration_input_score) in score_map.unwrap() {
-                    let consideration_score = consideration
-                        .response_curve
-                        .transform(consideration_input_score)
-                        .clamp(0.0, 1.0);
+                for (&target_entity, &consideration_input_score) in score_map {
+                    let consideration_score = consideration.score(consideration_input_score);
                     debug!(
                         "Consideration score for targeted system {} and entity {:?} is {:.2} (raw {:.2})",
                         consideration.input_name, target_entity, consideration_score, consideration_input_score
                     );
 
-                    *targeted_scores
-                        .entry(target_entity)
-                        .or_insert(decision_score) *= consideration_score;
+                    let (score, count) = targeted_scores
+                        .entry(vec![target_entity])
+                        .or_insert((decision_score, 0));
+                    *score *= compensated(consideration_score);
+                    *count += 1;
                 }
             }
 
-            for (entity, targeted_decision_score) in targeted_scores {
-                evaluated_decisions.push((idx, Some(entity), targeted_decision_score));
+            // consider multi targeted considerations
+            for consideration in &decision.multi_targeted_considerations {
+                let Some(score_map) = ai_meta
+                    .multi_targeted_input_scores
+                    .get(&consideration.input)
+                else {
+                    debug!(
+                        "No scores where registered for multi targeted input system {}",
+                        consideration.input_name
+                    );
+                    continue;
+                };
+                for (targets, &consideration_input_score) in score_map {
+                    let consideration_score = consideration.score(consideration_input_score);
+                    debug!(
+                        "Consideration score for multi targeted system {} and entities {:?} is {:.2} (raw {:.2})",
+                        consideration.input_name, targets, consideration_score, consideration_input_score
+                    );
+
+                    let (score, count) = targeted_scores
+                        .entry(targets.clone())
+                        .or_insert((decision_score, 0));
+                    *score *= compensated(consideration_score);
+                    *count += 1;
+                }
+            }
+
+            let targeted_consideration_count = decision.targeted_considerations.len()
+                + decision.multi_targeted_considerations.len();
+            for (targets, (mut targeted_decision_score, count)) in targeted_scores {
+                // targets missing a score were invalid for at least one of the considerations
+                if count < targeted_consideration_count {
+                    debug!("Decision {} for entities {:?} has an invalid target", idx, targets);
+                    continue;
+                }
+                targeted_decision_score *= decision.weight * penalty(decision, &targets);
                 debug!(
-                    "Decision {} for entity {:?} scored {:.2}",
-                    idx, entity, targeted_decision_score
+                    "Decision {} for entities {:?} scored {:.2}",
+                    idx, targets, targeted_decision_score
                 );
+                evaluated_decisions.push((idx, targets, targeted_decision_score));
             }
         }
 
         if evaluated_decisions.is_empty() {
             debug!("no scorable considerations for decision, skipping");
-            continue;
+            return;
         }
 
-        // pick best decision
-        evaluated_decisions.sort_by(|a, b| b.2.total_cmp(&a.2));
+        // only the decisions in the highest priority bucket with a viable decision are
+        // picked from, the other buckets are only considered once it has none left
+        if !ai_definition.buckets.is_empty() {
+            let priority = |idx: usize| ai_definition.bucket_priority(&ai_definition.decisions[idx]);
+            let best_priority = evaluated_decisions
+                .iter()
+                .filter(|(_, _, score)| *score > 0.0)
+                .map(|(idx, _, _)| priority(*idx))
+                .max_by(f32::total_cmp);
+            if let Some(best_priority) = best_priority {
+                debug!("picking from buckets with priority {:.2}", best_priority);
+                evaluated_decisions.retain(|(idx, _, _)| priority(*idx) == best_priority);
+            }
+        }
+
+        // apply momentum to the current action and target, so that we don't flip-flop between
+        // similarly scored options
+        let is_current = |(idx, targets, _): &(usize, Vec<Entity>, f32)| {
+            !action_finished
+                && Some(ai_definition.decisions[*idx].action) == ai_meta.current_action
+                && *targets == ai_meta.current_targets
+        };
+        let adjusted_score = |evaluated_decision: &(usize, Vec<Entity>, f32)| {
+            let &(idx, _, score) = evaluated_decision;
+            if is_current(evaluated_decision) {
+                let momentum = ai_definition.decisions[idx]
+                    .momentum
+                    .unwrap_or(ai_definition.momentum);
+                score * (1.0 + momentum)
+            } else {
+                score
+            }
+        };
+
+        // sort from best to worst, ties are broken by decision order and then by target so
+        // that the result doesn't depend on HashMap iteration order
+        evaluated_decisions.sort_by(|a, b| {
+            adjusted_score(b)
+                .total_cmp(&adjusted_score(a))
+                .then(a.0.cmp(&b.0))
+                .then(a.1.cmp(&b.1))
+        });
+
+        // pick a decision using the AI's selection strategy
+        let sorted_scores: Vec<f32> = evaluated_decisions.iter().map(adjusted_score).collect();
+        let selected_idx = ai_definition
+            .selection_strategy
+            .select(&sorted_scores, &mut AIRng::for_entity(seed, entity_id));
+        let mut selected_decision = &evaluated_decisions[selected_idx];
 
-        let (decision_idx, target, score) = evaluated_decisions.first().unwrap();
+        // only switch if the selected decision beats the current one by the switching margin
+        if !is_current(selected_decision) {
+            if let Some(current_decision) = evaluated_decisions.iter().find(|e| is_current(e))
+            {
+                if adjusted_score(selected_decision)
+                    < adjusted_score(current_decision) + ai_definition.switching_margin
+                {
+                    debug!(
+                        "Selected decision {} did not beat the current decision by the switching margin",
+                        selected_decision.0
+                    );
+                    selected_decision = current_decision;
+                }
+            }
+        }
+
+        let (decision_idx, targets, score) = selected_decision;
+        // the first target of a multi targeted decision is its primary target
+        let target = targets.first().copied();
         let Decision {
             action_name,
             action,
             is_targeted,
+            action_factory,
             ..
         } = &ai_definition.decisions[*decision_idx];
 
         let keep_current_action = Some(*action) == ai_meta.current_action;
-        let keep_current_target = *target == ai_meta.current_target;
+        let keep_current_target = *targets == ai_meta.current_targets;
+        // the action may be kept by a different decision, whose commitment applies from now on
+        ai_meta.current_decision = Some(*decision_idx);
+
+        if let Some(mut history) = history {
+            history.push(DecisionRecord {
+                tick: tick.0,
+                scores: evaluated_decisions
+                    .iter()
+                    .map(|(idx, targets, score)| DecisionScore {
+                        action: ai_definition.decisions[*idx].action_name.clone(),
+                        target: targets.first().copied(),
+                        targets: targets.clone(),
+                        score: *score,
+                    })
+                    .collect(),
+                action: action_name.clone(),
+                target,
+                targets: targets.clone(),
+                score: *score,
+                reason: if !keep_current_action {
+                    SwitchReason::NewAction
+                } else if !keep_current_target {
+                    SwitchReason::NewTarget
+                } else {
+                    SwitchReason::Kept
+                },
+            });
+        }
 
         if keep_current_action && keep_current_target {
             // Scenario 1: Same Action, keep same target (which can be None)
@@ -127,7 +381,6 @@ pub(crate) fn make_decisions(
                 );
             }
             ai_meta.current_action_score = *score;
-            continue;
         } else {
             if !keep_current_action {
                 // Scenario 3: New action
@@ -155,18 +408,42 @@ pub(crate) fn make_decisions(
 
             // Change our currection action, we do this in another system as it will
             // unfortunately require mut World access so isn't parallelisable.
-            event_writer.send(UpdateEntityAction {
+            update_entity_actions.lock().unwrap().push(UpdateEntityAction {
                 entity_id,
                 old_action: ai_meta.current_action,
                 new_action: *action,
+                old_action_name: ai_meta.current_action_name.clone(),
+                new_action_name: action_name.clone(),
                 old_target: ai_meta.current_target,
-                new_target: *target,
+                new_target: target,
+                old_targets: ai_meta.current_targets.clone(),
+                new_targets: targets.clone(),
+                old_score: ai_meta.current_action_score,
+                new_score: *score,
+                action_factory: action_factory.clone(),
             });
 
             ai_meta.current_action = Some(*action);
             ai_meta.current_action_name = action_name.clone();
             ai_meta.current_action_score = *score;
-            ai_meta.current_target = *target;
+            ai_meta.current_target = target;
+            ai_meta.current_targets = targets.clone();
+            ai_meta.current_action_ticks = 0;
+            ai_meta.current_action_started = time.elapsed();
         }
-    }
+    });
+
+    // sort the updates so that update_action applies them in a consistent order
+    let mut update_entity_actions = update_entity_actions.into_inner().unwrap();
+    update_entity_actions.sort_by_key(|update| update.entity_id);
+    event_writer.send_batch(update_entity_actions);
+}
+
+/// Applies the consideration compensation factor, this makes up some of the score lost by
+/// multiplying many consideration scores together so that decisions with a different number of
+/// considerations can be compared fairly.
+fn compensate_score(score: f32, consideration_count: usize) -> f32 {
+    let modification_factor = 1.0 - 1.0 / consideration_count as f32;
+    let make_up_value = (1.0 - score) * modification_factor;
+    score + make_up_value * score
 }
@@ -0,0 +1,30 @@
+use crate::{AIDefinitions, AIMeta, AITick, ActionStatus};
+use bevy::log::debug_span;
+use bevy::prelude::{Entity, Query, Res, ResMut};
+
+/// Advances the AITick and marks which entities are due to be evaluated on this tick, see
+/// DefineAI::set_decision_interval.
+pub(crate) fn schedule_decisions(
+    mut query: Query<(Entity, &mut AIMeta, Option<&ActionStatus>)>,
+    ai_definitions: Res<AIDefinitions>,
+    mut tick: ResMut<AITick>,
+) {
+    let _span = debug_span!("Scheduling Decisions").entered();
+    tick.0 += 1;
+
+    for (entity_id, mut ai_meta, action_status) in query.iter_mut() {
+        let Some(ai_definition) = ai_definitions.map.get(&ai_meta.ai_definition) else {
+            continue;
+        };
+
+        // entities without an action, or whose action has finished, shouldn't wait their turn
+        let needs_action = ai_meta.current_action.is_none()
+            || action_status.is_some_and(|status| *status != ActionStatus::Running);
+        let is_due = needs_action || ai_definition.is_scheduled(entity_id, tick.0);
+
+        // avoid triggering change detection on entities whose schedule hasn't changed
+        if ai_meta.is_due != is_due {
+            ai_meta.is_due = is_due;
+        }
+    }
+}
@@ -2,8 +2,9 @@ use bevy::prelude::{
     debug, debug_span, AppTypeRegistry, Events, ReflectComponent, ReflectDefault, World,
 };
 
+use crate::events::{ActionEnded, ActionStarted, TargetChanged};
 use crate::systems::UpdateEntityAction;
-use crate::ActionTarget;
+use crate::{ActionStatus, ActionTarget, ActionTargets};
 
 pub(crate) fn update_action(world: &mut World) {
     let _span = debug_span!("Updating Actions").entered();
@@ -14,6 +15,14 @@ pub(crate) fn update_action(world: &mut World) {
         .remove_resource::<Events<UpdateEntityAction>>()
         .unwrap();
 
+    // the public events are sent once all the entities have been updated
+    let mut actions_ended = Vec::new();
+    let mut actions_started = Vec::new();
+    let mut targets_changed = Vec::new();
+    // actions with a factory are built once the resources are back in the world, so that the
+    // factories can use them
+    let mut actions_to_build = Vec::new();
+
     if !events.is_empty() {
         debug!("{} Events to process", events.len());
         let registry_read = type_registry.read();
@@ -23,55 +32,127 @@ pub(crate) fn update_action(world: &mut World) {
                 entity_id,
                 old_action,
                 new_action,
+                old_action_name,
+                new_action_name,
                 old_target,
                 new_target,
+                old_targets,
+                new_targets,
+                old_score,
+                new_score,
+                action_factory,
             } = event;
 
             let _span = debug_span!("", entity = entity_id.index()).entered();
 
-            if let Some(mut entity_mut) = world.get_entity_mut(entity_id) {
-                // Update the action on the entity
-                if old_action != Some(new_action) {
-                    // Remove the old action component
-                    if let Some(old_action) = old_action {
-                        registry_read
-                            .get(old_action)
-                            .unwrap()
-                            .data::<ReflectComponent>()
-                            .unwrap()
-                            .remove(&mut entity_mut);
-                        debug!("Removed Action {:?}", old_action);
-                    }
+            let Some(mut entity_mut) = world.get_entity_mut(entity_id) else {
+                debug!("Unable to update Entity as it does not exist");
+                continue;
+            };
+
+            // a multi targeted action can change its targets while keeping its primary target
+            let is_new_target = old_targets != new_targets;
+
+            // Update the action on the entity
+            let is_new_action = old_action != Some(new_action);
+            if is_new_action {
+                // Remove the old action component
+                if let Some(old_action) = old_action {
+                    registry_read
+                        .get(old_action)
+                        .unwrap()
+                        .data::<ReflectComponent>()
+                        .unwrap()
+                        .remove(&mut entity_mut);
+                    debug!("Removed Action {:?}", old_action);
+                    actions_ended.push(ActionEnded {
+                        entity: entity_id,
+                        action: old_action,
+                        action_name: old_action_name,
+                        target: old_target,
+                        targets: old_targets.clone(),
+                        score: old_score,
+                    });
+                }
 
-                    // Add the new action component
-                    if let Some(registration) = registry_read.get(new_action) {
+                // Add the new action component, actions with a factory are built below once
+                // the target is up to date
+                if let Some(registration) = registry_read.get(new_action) {
+                    if action_factory.is_none() {
                         let reflect_default = registration.data::<ReflectDefault>().unwrap();
                         let reflect_component =
                             registration.data::<ReflectComponent>().unwrap();
                         reflect_component
                             .insert(&mut entity_mut, reflect_default.default().as_ref());
-                        debug!("Added Action {:?}", new_action);
-                    } else {
-                        panic!(
-                            "An Action Component was not found in the type registry: {:?}",
-                            new_action
-                        )
                     }
+                    entity_mut.insert(ActionStatus::Running);
+                    debug!("Added Action {:?}", new_action);
+                    actions_started.push(ActionStarted {
+                        entity: entity_id,
+                        action: new_action,
+                        action_name: new_action_name,
+                        target: new_target,
+                        targets: new_targets.clone(),
+                        score: new_score,
+                    });
                 } else {
-                    debug!("Unable to update Entity as it does not exist");
+                    panic!(
+                        "An Action Component was not found in the type registry: {:?}",
+                        new_action
+                    )
                 }
+            } else if is_new_target {
+                targets_changed.push(TargetChanged {
+                    entity: entity_id,
+                    action: new_action,
+                    action_name: new_action_name,
+                    old_target,
+                    new_target,
+                    old_targets: old_targets.clone(),
+                    new_targets: new_targets.clone(),
+                    score: new_score,
+                });
+            }
 
-                // Update the target on the entity
-                if old_target != new_target {
-                    if entity_mut.contains::<ActionTarget>() {
-                        entity_mut.remove::<ActionTarget>();
-                        debug!("Removed Target");
-                    }
+            // Update the target on the entity
+            if old_target != new_target {
+                if entity_mut.contains::<ActionTarget>() {
+                    entity_mut.remove::<ActionTarget>();
+                    debug!("Removed Target");
+                }
 
-                    if let Some(target) = new_target {
-                        entity_mut.insert(ActionTarget { target });
-                        debug!("Added Target {:?}", target);
-                    }
+                if let Some(target) = new_target {
+                    entity_mut.insert(ActionTarget { target });
+                    debug!("Added Target {:?}", target);
+                }
+            }
+
+            // only multi targeted actions carry the full set of targets
+            if is_new_target {
+                if entity_mut.contains::<ActionTargets>() {
+                    entity_mut.remove::<ActionTargets>();
+                    debug!("Removed Targets");
+                }
+
+                if new_targets.len() > 1 {
+                    debug!("Added Targets {:?}", new_targets);
+                    entity_mut.insert(ActionTargets {
+                        targets: new_targets.clone(),
+                    });
+                }
+            }
+
+            // actions built by a factory depend on their target, so they are rebuilt whenever
+            // it changes
+            if let Some(action_factory) = action_factory {
+                if is_new_action || is_new_target {
+                    actions_to_build.push((
+                        action_factory,
+                        entity_id,
+                        new_action,
+                        new_targets,
+                        new_score,
+                    ));
                 }
             }
         }
@@ -79,4 +160,14 @@ pub(crate) fn update_action(world: &mut World) {
 
     world.insert_resource(type_registry);
     world.insert_resource(events);
+
+    for (action_factory, entity_id, action, targets, score) in actions_to_build {
+        let _span = debug_span!("", entity = entity_id.index()).entered();
+        action_factory.insert(world, entity_id, &targets, score);
+        debug!("Built Action {:?}", action);
+    }
+
+    world.send_event_batch(actions_ended);
+    world.send_event_batch(actions_started);
+    world.send_event_batch(targets_changed);
 }
@@ -1,5 +1,7 @@
 use bevy::app::App;
+use bevy::core::TaskPoolPlugin;
 use bevy::log::LogPlugin;
+use bevy::time::TimePlugin;
 
 pub fn test_app() -> App {
     let mut app = App::new();
@@ -7,5 +9,7 @@ pub fn test_app() -> App {
         filter: "wgpu=error".into(),
         level: bevy::log::Level::DEBUG,
     });
+    app.add_plugin(TaskPoolPlugin::default());
+    app.add_plugin(TimePlugin);
     app
 }
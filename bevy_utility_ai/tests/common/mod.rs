@@ -28,6 +28,14 @@ pub struct ActionOne {}
 #[reflect(Component, Default)]
 pub struct ActionTwo {}
 
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ActionMoveTo {
+    pub destination: Vec2,
+    pub score: f32,
+    pub input_score: f32,
+}
+
 // AI Marker Components
 #[derive(Component)]
 pub struct AI {}
@@ -44,3 +52,6 @@ pub struct AA {}
 
 #[derive(Component)]
 pub struct BB {}
+
+#[derive(Component)]
+pub struct CC {}
@@ -1,17 +1,34 @@
 use std::any::TypeId;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use bevy::prelude::{Entity, Vec2};
+use bevy::app::App;
+use bevy::asset::{AddAsset, AssetPlugin, Assets};
+use bevy::core::TaskPoolPlugin;
+use bevy::prelude::{AppTypeRegistry, Entity, Events, Query, Vec2, Vec3};
 
 use bevy_utility_ai::ai_meta::AIMeta;
 use bevy_utility_ai::considerations::Consideration;
-use bevy_utility_ai::define_ai::DefineAI;
+use bevy_utility_ai::define_ai::{DecisionSettings, DefineAI};
+use bevy_utility_ai::definition_asset::{
+    AIDefinitionAsset, AIDefinitionAssetAppExt, AIDefinitionConfig,
+};
+use bevy_utility_ai::events::{ActionEnded, ActionStarted, TargetChanged};
+use bevy_utility_ai::history::{AIDecisionHistory, DecisionRecord, SwitchReason};
 use bevy_utility_ai::plugin::UtilityAIPlugin;
+use bevy_utility_ai::registry::{AIRegistry, RegisterAIAppExt};
 use bevy_utility_ai::response_curves::LinearCurve;
-use bevy_utility_ai::{input_system, targeted_input_system, AITargetEntitySets};
+use bevy_utility_ai::selection::{AIRng, SelectionStrategy};
+use bevy_utility_ai::spatial::{AIPosition, AISpatialIndexAppExt};
+use bevy_utility_ai::{
+    input_system, multi_targeted_input_system, target_selector, targeted_input_system,
+    AIDefinitions, AITargetEntitySets, ActionStatus, ActionTarget, ActionTargets, Commitment,
+    InputId,
+};
 
 use crate::common::app::test_app;
 use crate::common::{
-    ActionOne, ActionTwo, Position, SomeData, SomeOtherData, AA, AI, AI1, AI2,
+    ActionMoveTo, ActionOne, ActionTwo, Position, SomeData, SomeOtherData, AA, AI, AI1, AI2,
+    BB, CC,
 };
 
 mod common;
@@ -117,17 +134,17 @@ fn calculate_inputs_calculates_only_for_required_entities() {
 
     assert!(ai_meta_1
         .input_scores
-        .contains_key(&(utility_input_1 as usize)));
+        .contains_key(&InputId::of(utility_input_1)));
     assert!(!ai_meta_1
         .input_scores
-        .contains_key(&(utility_input_2 as usize)));
+        .contains_key(&InputId::of(utility_input_2)));
 
     assert!(!ai_meta_2
         .input_scores
-        .contains_key(&(utility_input_1 as usize)));
+        .contains_key(&InputId::of(utility_input_1)));
     assert!(ai_meta_2
         .input_scores
-        .contains_key(&(utility_input_2 as usize)));
+        .contains_key(&InputId::of(utility_input_2)));
 }
 
 /// This test checks whether the framework correctly chooses the highest scoring decision in the
@@ -239,17 +256,17 @@ fn calculate_targeted_inputs_calculates_only_for_required_entities() {
 
     assert!(ai_meta_1
         .targeted_input_scores
-        .contains_key(&(targeted_utility_input_1 as usize)));
+        .contains_key(&InputId::of(targeted_utility_input_1)));
     assert!(!ai_meta_1
         .targeted_input_scores
-        .contains_key(&(targeted_utility_input_2 as usize)));
+        .contains_key(&InputId::of(targeted_utility_input_2)));
 
     assert!(!ai_meta_2
         .targeted_input_scores
-        .contains_key(&(targeted_utility_input_1 as usize)));
+        .contains_key(&InputId::of(targeted_utility_input_1)));
     assert!(ai_meta_2
         .targeted_input_scores
-        .contains_key(&(targeted_utility_input_2 as usize)));
+        .contains_key(&InputId::of(targeted_utility_input_2)));
 }
 
 /// This test checks that the framework correctly handles targeted_filter systems.
@@ -305,7 +322,7 @@ fn calculate_targeted_inputs_respects_filters() {
 
     let scores = ai_meta
         .targeted_input_scores
-        .get(&(targeted_utility_input_1 as usize))
+        .get(&InputId::of(targeted_utility_input_1))
         .unwrap();
 
     assert!(scores.contains_key(&entity_target));
@@ -319,3 +336,2283 @@ fn calculate_targeted_inputs_respects_filters() {
     assert_eq!(entity_set.len(), 1);
     assert!(entity_set.contains(&entity_target));
 }
+
+/// This test checks that targets which lose their filter component or are despawned are removed
+/// from the filter sets and that their scores are purged.
+#[test]
+fn calculate_targeted_inputs_purges_removed_targets() {
+    // SETUP
+    #[targeted_input_system]
+    fn targeted_utility_input_1(subject: (&Position,), target: (&Position,)) -> f32 {
+        subject.0.val.distance(target.0.val)
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI1>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::targeted_filter::<AA>(),
+            Consideration::targeted(targeted_utility_input_1)
+                .set_input_name("targeted_utility_input_1".into()),
+        ])
+        .register(&mut app);
+
+    let entity_subject = app
+        .world
+        .spawn((
+            Position {
+                val: Vec2::new(1.0, 1.0),
+            },
+            AI1 {},
+            AIMeta::new::<AI1>(),
+        ))
+        .id();
+    let entity_removed = app
+        .world
+        .spawn((
+            Position {
+                val: Vec2::new(0.0, 0.0),
+            },
+            AA {},
+        ))
+        .id();
+    let entity_despawned = app
+        .world
+        .spawn((
+            Position {
+                val: Vec2::new(-1.0, -1.0),
+            },
+            AA {},
+        ))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_subject).unwrap();
+    let scores = ai_meta
+        .targeted_input_scores
+        .get(&InputId::of(targeted_utility_input_1))
+        .unwrap();
+    assert_eq!(scores.len(), 2);
+
+    app.world.entity_mut(entity_removed).remove::<AA>();
+    app.world.despawn(entity_despawned);
+
+    // Double update so that the filter sets are updated before the inputs are recalculated
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_subject).unwrap();
+    let scores = ai_meta
+        .targeted_input_scores
+        .get(&InputId::of(targeted_utility_input_1))
+        .unwrap();
+    assert!(scores.is_empty());
+
+    let ai_target_entity_sets = app.world.get_resource::<AITargetEntitySets>().unwrap();
+    assert!(ai_target_entity_sets
+        .get(Consideration::targeted_filter::<AA>().input)
+        .is_empty());
+}
+
+/// This test checks that the framework correctly combines require and exclude targeted filters.
+#[test]
+fn calculate_targeted_inputs_respects_require_and_exclude_filters() {
+    // SETUP
+    #[targeted_input_system]
+    fn targeted_utility_input_1(subject: (&Position,), target: (&Position,)) -> f32 {
+        subject.0.val.distance(target.0.val)
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI1>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::targeted_require_filter::<AA>(),
+            Consideration::targeted_require_filter::<BB>(),
+            Consideration::targeted_exclude_filter::<CC>(),
+            Consideration::targeted(targeted_utility_input_1)
+                .set_input_name("targeted_utility_input_1".into()),
+        ])
+        .register(&mut app);
+
+    let position = Position {
+        val: Vec2::new(0.0, 0.0),
+    };
+    let entity_subject = app
+        .world
+        .spawn((
+            Position {
+                val: Vec2::new(1.0, 1.0),
+            },
+            AI1 {},
+            AIMeta::new::<AI1>(),
+        ))
+        .id();
+    let entity_target = app
+        .world
+        .spawn((Position { ..position }, AA {}, BB {}))
+        .id();
+    let entity_missing_required = app.world.spawn((Position { ..position }, AA {})).id();
+    let entity_excluded = app
+        .world
+        .spawn((Position { ..position }, AA {}, BB {}, CC {}))
+        .id();
+
+    // Double update so that the filter sets are populated before the inputs are calculated
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_subject).unwrap();
+    let scores = ai_meta
+        .targeted_input_scores
+        .get(&InputId::of(targeted_utility_input_1))
+        .unwrap();
+
+    assert_eq!(scores.len(), 1);
+    assert!(scores.contains_key(&entity_target));
+    assert!(!scores.contains_key(&entity_missing_required));
+    assert!(!scores.contains_key(&entity_excluded));
+}
+
+/// This test checks that momentum keeps the current action until another decision beats it by
+/// more than the momentum bonus.
+#[test]
+fn momentum_prevents_switching_between_similar_decisions() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .set_momentum(0.2)
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1)])
+        .add_decision_with_settings::<ActionTwo>(
+            vec![Consideration::simple(utility_input_2)],
+            DecisionSettings::default().with_momentum(0.0),
+        )
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.6 },
+            SomeOtherData { val: 0.5 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    // 0.65 does not beat 0.6 * 1.2, so we keep ActionOne
+    app.world.get_mut::<SomeOtherData>(entity_id).unwrap().val = 0.65;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    assert_eq!(ai_meta.current_action_score, 0.6);
+
+    // 0.75 beats 0.6 * 1.2, so we switch to ActionTwo
+    app.world.get_mut::<SomeOtherData>(entity_id).unwrap().val = 0.75;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+
+    // ActionTwo has no momentum, so we switch back as soon as ActionOne is higher
+    app.world.get_mut::<SomeData>(entity_id).unwrap().val = 0.8;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+}
+
+/// This test checks that a new decision has to beat the current one by the switching margin.
+#[test]
+fn switching_margin_prevents_switching_between_similar_decisions() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .set_switching_margin(0.1)
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_2)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.5 },
+            SomeOtherData { val: 0.4 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    app.world.get_mut::<SomeOtherData>(entity_id).unwrap().val = 0.55;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    app.world.get_mut::<SomeOtherData>(entity_id).unwrap().val = 0.65;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+}
+
+/// This test checks that decision weights are applied to the decision's score.
+#[test]
+fn decision_weight_multiplies_score() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision_with_settings::<ActionOne>(
+            vec![Consideration::simple(utility_input_low)],
+            DecisionSettings::default().with_weight(4.0),
+        )
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+
+    assert_eq!(ai_meta.current_action_score, 1.0);
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+}
+
+/// This test checks that an action is kept until its minimum commitment has been met.
+#[test]
+fn min_commitment_keeps_action() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision_with_settings::<ActionOne>(
+            vec![Consideration::simple(utility_input_1)],
+            DecisionSettings::default().with_min_commitment(Commitment::Ticks(3)),
+        )
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_2)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.75 },
+            SomeOtherData { val: 0.25 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    app.world.get_mut::<SomeData>(entity_id).unwrap().val = 0.0;
+
+    // the action is kept for the next two ticks, and re-evaluated on the third
+    for _ in 0..2 {
+        app.update();
+        let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+        assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    }
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+}
+
+/// This test checks that the minimum commitment of the decision that chose the action is used
+/// when several decisions share an action.
+#[test]
+fn min_commitment_is_from_chosen_decision() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    #[input_system]
+    fn utility_input_3(some_data: &SomeData) -> f32 {
+        1.0 - some_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_2)])
+        .add_decision_with_settings::<ActionOne>(
+            vec![Consideration::simple(utility_input_1)],
+            DecisionSettings::default().with_min_commitment(Commitment::Ticks(3)),
+        )
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_3)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.75 },
+            SomeOtherData { val: 0.0 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    assert_eq!(ai_meta.current_decision, Some(1));
+
+    app.world.get_mut::<SomeData>(entity_id).unwrap().val = 0.0;
+
+    // the second decision's commitment keeps the action for the next two ticks
+    for _ in 0..2 {
+        app.update();
+        let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+        assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    }
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    assert_eq!(ai_meta.current_decision, Some(2));
+}
+
+/// This test checks that the compensation factor lets a decision with many considerations
+/// compete with a decision with one consideration.
+#[test]
+fn compensate_considerations_favours_multiple_considerations() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    // without compensation ActionOne scores 0.8 * 0.8 = 0.64 so ActionTwo would win with 0.7
+    DefineAI::<AI>::new()
+        .set_compensate_considerations(true)
+        .add_decision::<ActionOne>(vec![
+            Consideration::simple(utility_input_1),
+            Consideration::simple(utility_input_1),
+        ])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_2)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.8 },
+            SomeOtherData { val: 0.7 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    assert!((ai_meta.current_action_score - 0.88 * 0.88).abs() < 1e-6);
+}
+
+/// This test checks that a consideration whose score is NaN scores zero rather than beating
+/// every other decision.
+#[test]
+fn nan_consideration_scores_zero() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_2)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: f32::NAN },
+            SomeOtherData { val: 0.25 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    // TEST
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    assert_eq!(ai_meta.current_action_score, 0.25);
+}
+
+/// This test checks that ties between decisions are broken by the order they were added.
+#[test]
+fn highest_selection_breaks_ties_by_decision_order() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .set_selection_strategy(SelectionStrategy::Highest)
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_1)])
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((SomeData { val: 0.5 }, AI {}, AIMeta::new::<AI>()))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+}
+
+/// This test checks that inputs are normalised by the consideration's input range before the
+/// response curve is applied.
+#[test]
+fn input_range_normalises_inputs() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::simple(utility_input_1).with_input_range(0.0, 1000.0)
+        ])
+        .add_decision::<ActionTwo>(vec![
+            Consideration::simple(utility_input_2).with_log_input_range(1.0, 1000.0)
+        ])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 250.0 },
+            SomeOtherData { val: 10.0 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    // 10 is a third of the way between 1 and 1000 on a log scale, so beats 250 / 1000
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    assert!((ai_meta.current_action_score - 1.0 / 3.0).abs() < 1e-6);
+}
+
+/// This test checks that the plugin requires the TaskPoolPlugin, which sets up the
+/// ComputeTaskPool used by the parallel queries.
+#[test]
+#[should_panic(expected = "The UtilityAIPlugin requires the TaskPoolPlugin")]
+fn plugin_requires_task_pool_plugin() {
+    App::new().add_plugin(UtilityAIPlugin);
+}
+
+/// This test checks that the plugin requires the TimePlugin, as Duration commitments and
+/// failure penalties would otherwise never expire.
+#[test]
+#[should_panic(expected = "The UtilityAIPlugin requires the TimePlugin")]
+fn plugin_requires_time_plugin() {
+    App::new()
+        .add_plugin(TaskPoolPlugin::default())
+        .add_plugin(UtilityAIPlugin);
+}
+
+/// This test checks that an input range must have a max greater than its min, as it would
+/// otherwise normalise inputs to NaN.
+#[test]
+#[should_panic(expected = "An input range must have a max greater than its min")]
+fn input_range_must_have_max_greater_than_min() {
+    #[input_system]
+    fn utility_input(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    Consideration::simple(utility_input).with_input_range(1.0, 1.0);
+}
+
+/// This test checks that an input range must be finite, the same as for an input range loaded
+/// from an AIDefinitionAsset.
+#[test]
+#[should_panic(expected = "An input range must have a finite min and max")]
+fn input_range_must_be_finite() {
+    #[input_system]
+    fn utility_input(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    Consideration::simple(utility_input).with_input_range(0.0, f32::INFINITY);
+}
+
+/// This test checks that a decision's weight must be finite and not negative, the same as for
+/// a decision loaded from an AIDefinitionAsset.
+#[test]
+#[should_panic(expected = "The weight of a decision must be finite and not negative")]
+fn decision_weight_must_not_be_negative() {
+    #[input_system]
+    fn utility_input(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    DefineAI::<AI>::new().add_decision_with_settings::<ActionOne>(
+        vec![Consideration::simple(utility_input)],
+        DecisionSettings::default().with_weight(-1.0),
+    );
+}
+
+/// This test checks that an AI can be defined from an AIDefinitionAsset using inputs and actions
+/// registered by name, and that modifying the asset replaces the AIDefinition.
+#[test]
+fn ai_definition_asset_defines_and_reloads_ai() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(AssetPlugin::default())
+        .add_plugin(UtilityAIPlugin)
+        .register_ai_input("utility_input_low", utility_input_low)
+        .register_ai_input("utility_input_high", utility_input_high)
+        .register_ai_action::<ActionOne>("action_one")
+        .register_ai_action::<ActionTwo>("action_two")
+        .add_asset::<AIDefinitionAsset>();
+
+    let config = ron::from_str::<AIDefinitionConfig>(
+        r#"(
+            decisions: [
+                (action: "action_one", considerations: [Input(input: "utility_input_low")]),
+                (action: "action_two", considerations: [Input(input: "utility_input_high")]),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    let handle =
+        app.world
+            .resource_mut::<Assets<AIDefinitionAsset>>()
+            .add(AIDefinitionAsset {
+                config: config.clone(),
+            });
+    app.add_ai_definition_asset::<AI>(handle.clone());
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    // The asset event is sent at the end of the first update
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+
+    // Weight the first decision so that it wins
+    let mut modified_config = config;
+    modified_config.decisions[0].weight = 4.0;
+    app.world
+        .resource_mut::<Assets<AIDefinitionAsset>>()
+        .get_mut(&handle)
+        .unwrap()
+        .config = modified_config;
+
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    assert_eq!(ai_meta.current_action_score, 1.0);
+}
+
+/// This test checks that modifying an AIDefinitionAsset to be invalid keeps the previous
+/// AIDefinition rather than crashing.
+#[test]
+fn invalid_ai_definition_asset_keeps_previous_ai() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(AssetPlugin::default())
+        .add_plugin(UtilityAIPlugin)
+        .register_ai_input("utility_input_low", utility_input_low)
+        .register_ai_input("utility_input_high", utility_input_high)
+        .register_ai_action::<ActionOne>("action_one")
+        .register_ai_action::<ActionTwo>("action_two")
+        .add_asset::<AIDefinitionAsset>();
+
+    let config = ron::from_str::<AIDefinitionConfig>(
+        r#"(
+            decisions: [
+                (action: "action_one", considerations: [Input(input: "utility_input_low")]),
+                (action: "action_two", considerations: [Input(input: "utility_input_high")]),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    let handle = app
+        .world
+        .resource_mut::<Assets<AIDefinitionAsset>>()
+        .add(AIDefinitionAsset { config });
+    app.add_ai_definition_asset::<AI>(handle.clone());
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+
+    // TEST
+    for invalid_decision in [
+        r#"(action: "action_one", weight: 4.0, considerations: [
+            Input(input: "utility_input_low", response_curve: Some(PiecewiseLinear(points: []))),
+        ])"#,
+        r#"(action: "action_one", weight: 4.0, considerations: [
+            Input(input: "utility_input_low", input_range: Some(Log(min: 0.0, max: 1.0))),
+        ])"#,
+        r#"(action: "action_one", weight: 4.0, considerations: [
+            Input(input: "utility_input_low", input_range: Some(Linear(min: 1.0, max: 0.0))),
+        ])"#,
+        r#"(action: "action_one", weight: inf, considerations: [
+            Input(input: "utility_input_low"),
+        ])"#,
+        r#"(action: "action_one", weight: 4.0, bucket: Some("Survival"), considerations: [
+            Input(input: "utility_input_low"),
+        ])"#,
+    ] {
+        let invalid_config = ron::from_str::<AIDefinitionConfig>(&format!(
+            r#"(
+                decisions: [
+                    {},
+                    (action: "action_two", considerations: [Input(input: "utility_input_high")]),
+                ],
+            )"#,
+            invalid_decision
+        ))
+        .unwrap();
+        app.world
+            .resource_mut::<Assets<AIDefinitionAsset>>()
+            .get_mut(&handle)
+            .unwrap()
+            .config = invalid_config;
+
+        app.update();
+        app.update();
+
+        let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+        assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    }
+}
+
+/// This test checks that an AIDefinitionAsset with invalid curve bounds keeps the previous
+/// AIDefinition rather than crashing when the curve is evaluated.
+#[test]
+fn invalid_curve_bounds_in_asset_keep_previous_ai() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(AssetPlugin::default())
+        .add_plugin(UtilityAIPlugin)
+        .register_ai_input("utility_input_low", utility_input_low)
+        .register_ai_input("utility_input_high", utility_input_high)
+        .register_ai_action::<ActionOne>("action_one")
+        .register_ai_action::<ActionTwo>("action_two")
+        .add_asset::<AIDefinitionAsset>();
+
+    let config = ron::from_str::<AIDefinitionConfig>(
+        r#"(
+            decisions: [
+                (action: "action_one", considerations: [Input(input: "utility_input_low")]),
+                (action: "action_two", considerations: [Input(input: "utility_input_high")]),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    let handle = app
+        .world
+        .resource_mut::<Assets<AIDefinitionAsset>>()
+        .add(AIDefinitionAsset { config });
+    app.add_ai_definition_asset::<AI>(handle.clone());
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    // TEST
+    for invalid_curve in [
+        "Clamped(curve: Linear(slope: 1.0), min: 1.0, max: 0.0)",
+        "Clamped(curve: Linear(slope: 1.0), min: NaN, max: 1.0)",
+        "Remapped(curve: Linear(slope: 1.0), min: 0.0, max: inf)",
+    ] {
+        let invalid_config = ron::from_str::<AIDefinitionConfig>(&format!(
+            r#"(
+                decisions: [
+                    (action: "action_one", weight: 4.0, considerations: [
+                        Input(input: "utility_input_low", response_curve: Some({})),
+                    ]),
+                    (action: "action_two", considerations: [Input(input: "utility_input_high")]),
+                ],
+            )"#,
+            invalid_curve
+        ))
+        .unwrap();
+        app.world
+            .resource_mut::<Assets<AIDefinitionAsset>>()
+            .get_mut(&handle)
+            .unwrap()
+            .config = invalid_config;
+
+        app.update();
+        app.update();
+
+        let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+        assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    }
+}
+
+/// This test checks that registered inputs can be looked up by the name they were registered
+/// under, and that the InputIds of inputs added by DefineAI are derived from the input system's
+/// name.
+#[test]
+fn registry_looks_up_input_ids_by_name() {
+    // SETUP
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_2(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[targeted_input_system]
+    fn targeted_utility_input_1(target: (&SomeData,)) -> f32 {
+        target.0.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin)
+        .register_ai_input("registered_input", utility_input_2);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1)])
+        .add_decision::<ActionTwo>(vec![
+            Consideration::targeted(targeted_utility_input_1),
+            Consideration::targeted_filter::<AA>(),
+        ])
+        .register(&mut app);
+
+    let registry = app.world.resource::<AIRegistry>();
+
+    // registered inputs are looked up by their registered name
+    assert_eq!(
+        registry.input_id("registered_input"),
+        Some(InputId::of(utility_input_2))
+    );
+
+    // inputs are named after their module path and function name
+    let input_name = concat!(module_path!(), "::utility_input_1");
+    assert_eq!(InputId::of(utility_input_1), InputId::from_name(input_name));
+    assert_eq!(
+        registry.input_name(InputId::of(utility_input_1)),
+        Some(input_name)
+    );
+    let filter_name = format!("targeted_filter_{}", std::any::type_name::<AA>());
+    assert_eq!(
+        registry.input_name(InputId::filter::<AA>()),
+        Some(filter_name.as_str())
+    );
+
+    // only registered inputs have a registered name
+    assert_eq!(registry.input_id(input_name), None);
+    assert_eq!(registry.input_id("utility_input_1"), None);
+}
+
+mod input_names {
+    pub mod a {
+        use crate::common::SomeData;
+        use bevy_utility_ai::input_system;
+
+        #[input_system]
+        pub fn utility_input(some_data: &SomeData) -> f32 {
+            some_data.val
+        }
+    }
+
+    pub mod b {
+        use crate::common::SomeData;
+        use bevy_utility_ai::input_system;
+
+        #[input_system]
+        pub fn utility_input(some_data: &SomeData) -> f32 {
+            some_data.val
+        }
+
+        #[input_system(name = "a::utility_input")]
+        pub fn clashing_utility_input(some_data: &SomeData) -> f32 {
+            some_data.val
+        }
+    }
+}
+
+/// This test checks that input systems with the same name in different modules get different
+/// InputIds, and that a name can be given instead.
+#[test]
+fn input_ids_include_module_path() {
+    // SETUP
+    #[input_system(name = "named_utility_input")]
+    fn utility_input(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::simple(input_names::a::utility_input),
+            Consideration::simple(input_names::b::utility_input),
+            Consideration::simple(utility_input),
+        ])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((AI {}, SomeData { val: 0.5 }, AIMeta::new::<AI>()))
+        .id();
+
+    // TEST
+    app.update();
+
+    let registry = app.world.resource::<AIRegistry>();
+    assert_ne!(
+        InputId::of(input_names::a::utility_input),
+        InputId::of(input_names::b::utility_input)
+    );
+    assert_eq!(
+        registry.input_name(InputId::of(input_names::a::utility_input)),
+        Some(concat!(module_path!(), "::input_names::a::utility_input"))
+    );
+    assert_eq!(
+        registry.input_name(InputId::of(input_names::b::utility_input)),
+        Some(concat!(module_path!(), "::input_names::b::utility_input"))
+    );
+    assert_eq!(
+        InputId::of(utility_input),
+        InputId::from_name("named_utility_input")
+    );
+
+    // each input is scored under its own InputId
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.input_scores.len(), 3);
+}
+
+/// This test checks that adding two input systems with the same InputId panics rather than
+/// silently sharing one score.
+#[test]
+#[should_panic(expected = "have the same InputId")]
+fn input_systems_with_same_input_id_panic() {
+    // SETUP
+    #[input_system(name = "utility_input")]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system(name = "utility_input")]
+    fn utility_input_2(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    // TEST
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_2)])
+        .register(&mut app);
+}
+
+/// This test checks that entities with an AIDecisionHistory record every evaluation of their
+/// decisions, including why the chosen action was switched to or kept.
+#[test]
+fn decision_history_records_evaluations() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+            AIDecisionHistory::new(8),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    let history = app.world.get::<AIDecisionHistory>(entity_id).unwrap();
+    let records: Vec<&DecisionRecord> = history.records().collect();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].tick, 1);
+    assert_eq!(records[0].reason, SwitchReason::NewAction);
+    assert_eq!(records[1].tick, 2);
+    assert_eq!(records[1].reason, SwitchReason::Kept);
+    assert!(records[1].action.ends_with("ActionTwo"));
+    assert_eq!(records[1].score, 0.75);
+    assert_eq!(
+        records[1]
+            .scores
+            .iter()
+            .map(|decision_score| decision_score.score)
+            .collect::<Vec<f32>>(),
+        vec![0.75, 0.25]
+    );
+    assert_eq!(history.to_json_lines().lines().count(), 2);
+}
+
+/// This test checks that ActionStarted and ActionEnded events are sent when an entity switches
+/// action, and that TargetChanged is sent when it only switches target.
+#[test]
+fn action_lifecycle_events_are_sent() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[targeted_input_system]
+    fn targeted_utility_input(target: (&SomeOtherData,)) -> f32 {
+        target.0.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .add_decision::<ActionTwo>(vec![Consideration::targeted(targeted_utility_input)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((SomeData { val: 0.25 }, AI {}, AIMeta::new::<AI>()))
+        .id();
+    let target_1 = app.world.spawn(SomeOtherData { val: 0.75 }).id();
+    let target_2 = app.world.spawn(SomeOtherData { val: 0.5 }).id();
+
+    let mut started_reader = app.world.resource::<Events<ActionStarted>>().get_reader();
+    let mut ended_reader = app.world.resource::<Events<ActionEnded>>().get_reader();
+    let mut target_reader = app.world.resource::<Events<TargetChanged>>().get_reader();
+
+    app.update();
+
+    let started: Vec<ActionStarted> = started_reader
+        .iter(app.world.resource::<Events<ActionStarted>>())
+        .cloned()
+        .collect();
+    assert_eq!(started.len(), 1);
+    assert_eq!(started[0].entity, entity_id);
+    assert_eq!(started[0].action, TypeId::of::<ActionTwo>());
+    assert_eq!(started[0].target, Some(target_1));
+    assert_eq!(started[0].score, 0.75);
+    assert_eq!(
+        ended_reader
+            .iter(app.world.resource::<Events<ActionEnded>>())
+            .count(),
+        0
+    );
+
+    // switch target
+    app.world.get_mut::<SomeOtherData>(target_2).unwrap().val = 1.0;
+    app.update();
+
+    let target_changed: Vec<TargetChanged> = target_reader
+        .iter(app.world.resource::<Events<TargetChanged>>())
+        .cloned()
+        .collect();
+    assert_eq!(target_changed.len(), 1);
+    assert_eq!(target_changed[0].old_target, Some(target_1));
+    assert_eq!(target_changed[0].new_target, Some(target_2));
+    assert_eq!(
+        started_reader
+            .iter(app.world.resource::<Events<ActionStarted>>())
+            .count(),
+        0
+    );
+
+    // switch action
+    app.world.get_mut::<SomeData>(entity_id).unwrap().val = 1.5;
+    app.update();
+
+    let ended: Vec<ActionEnded> = ended_reader
+        .iter(app.world.resource::<Events<ActionEnded>>())
+        .cloned()
+        .collect();
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].action, TypeId::of::<ActionTwo>());
+    assert_eq!(ended[0].target, Some(target_2));
+    assert_eq!(ended[0].score, 1.0);
+
+    let started: Vec<ActionStarted> = started_reader
+        .iter(app.world.resource::<Events<ActionStarted>>())
+        .cloned()
+        .collect();
+    assert_eq!(started.len(), 1);
+    assert_eq!(started[0].action, TypeId::of::<ActionOne>());
+    assert_eq!(started[0].target, None);
+}
+
+/// This test checks that ActionStatus::Success makes the entity re-decide straight away, even
+/// though it is committed to its current action.
+#[test]
+fn action_success_forces_redecision() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .add_decision_with_settings::<ActionTwo>(
+            vec![Consideration::simple(utility_input_high)],
+            DecisionSettings::default().with_min_commitment(Commitment::Ticks(100)),
+        )
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+    assert_eq!(
+        app.world.get::<ActionStatus>(entity_id),
+        Some(&ActionStatus::Running)
+    );
+
+    app.world.get_mut::<SomeOtherData>(entity_id).unwrap().val = 0.0;
+    app.update();
+
+    // still committed
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+
+    *app.world.get_mut::<ActionStatus>(entity_id).unwrap() = ActionStatus::Success;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+    assert_eq!(
+        app.world.get::<ActionStatus>(entity_id),
+        Some(&ActionStatus::Running)
+    );
+}
+
+/// This test checks that ActionStatus::Failure penalises the failed action and target pair, so
+/// that the entity switches to another target until the penalty expires.
+#[test]
+fn action_failure_penalises_action_and_target() {
+    // SETUP
+    #[targeted_input_system]
+    fn targeted_utility_input(target: (&SomeOtherData,)) -> f32 {
+        target.0.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::targeted(targeted_utility_input)])
+        .set_failure_penalty(0.5, Commitment::Ticks(2))
+        .register(&mut app);
+
+    let entity_id = app.world.spawn((AI {}, AIMeta::new::<AI>())).id();
+    let target_1 = app.world.spawn(SomeOtherData { val: 0.75 }).id();
+    let target_2 = app.world.spawn(SomeOtherData { val: 0.5 }).id();
+
+    app.update();
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_target, Some(target_1));
+
+    // 0.75 * 0.5 loses to 0.5
+    *app.world.get_mut::<ActionStatus>(entity_id).unwrap() = ActionStatus::Failure;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_target, Some(target_2));
+    assert_eq!(ai_meta.action_failures.len(), 1);
+
+    // the penalty expires
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_target, Some(target_1));
+    assert!(ai_meta.action_failures.is_empty());
+}
+
+/// This test checks that a failure penalty in ticks lasts for that many of the entity's
+/// evaluations when it has a decision interval, rather than that many AITicks.
+#[test]
+fn failure_penalty_ticks_count_evaluations() {
+    // SETUP
+    #[targeted_input_system]
+    fn targeted_utility_input(target: (&SomeOtherData,)) -> f32 {
+        target.0.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::targeted(targeted_utility_input)])
+        .set_failure_penalty(0.5, Commitment::Ticks(2))
+        .set_decision_interval(4)
+        .register(&mut app);
+
+    let entity_id = app.world.spawn((AI {}, AIMeta::new::<AI>())).id();
+    let target_1 = app.world.spawn(SomeOtherData { val: 0.75 }).id();
+    let target_2 = app.world.spawn(SomeOtherData { val: 0.5 }).id();
+
+    app.update();
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_target, Some(target_1));
+
+    // a failed action is re-decided straight away
+    *app.world.get_mut::<ActionStatus>(entity_id).unwrap() = ActionStatus::Failure;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_target, Some(target_2));
+    let evaluations = ai_meta.evaluations;
+
+    // TEST
+    // the entity is evaluated once every 4 ticks, the penalty outlasts its next evaluation
+    for _ in 0..4 {
+        app.update();
+    }
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.evaluations, evaluations + 1);
+    assert_eq!(ai_meta.current_target, Some(target_2));
+    assert_eq!(ai_meta.action_failures.len(), 1);
+
+    // and expires on the one after
+    for _ in 0..4 {
+        app.update();
+    }
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.evaluations, evaluations + 2);
+    assert_eq!(ai_meta.current_target, Some(target_1));
+    assert!(ai_meta.action_failures.is_empty());
+}
+
+/// This test checks that with a decision interval only a slice of the entities have their
+/// inputs calculated and decisions made each tick, and that every entity is evaluated once per
+/// interval.
+#[test]
+fn decision_interval_staggers_evaluation() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .set_decision_interval(4)
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .register(&mut app);
+
+    let entity_ids: Vec<Entity> = (0..8)
+        .map(|_| {
+            app.world
+                .spawn((
+                    SomeData { val: 0.25 },
+                    SomeOtherData { val: 0.75 },
+                    AI {},
+                    AIMeta::new::<AI>(),
+                ))
+                .id()
+        })
+        .collect();
+
+    // entities without an action are evaluated straight away
+    app.update();
+    for &entity_id in &entity_ids {
+        let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+        assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    }
+
+    for &entity_id in &entity_ids {
+        app.world.get_mut::<SomeOtherData>(entity_id).unwrap().val = 0.0;
+    }
+
+    let switched = |app: &bevy::prelude::App| {
+        entity_ids
+            .iter()
+            .filter(|&&entity_id| {
+                let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+                ai_meta.current_action == Some(TypeId::of::<ActionOne>())
+            })
+            .count()
+    };
+
+    // a quarter of the entities are evaluated each tick
+    app.update();
+    assert_eq!(switched(&app), 2);
+    let stale_inputs = entity_ids
+        .iter()
+        .filter(|&&entity_id| {
+            let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+            ai_meta.input_scores[&InputId::of(utility_input_high)] == 0.75
+        })
+        .count();
+    assert_eq!(stale_inputs, 6);
+
+    app.update();
+    assert_eq!(switched(&app), 4);
+    app.update();
+    app.update();
+    assert_eq!(switched(&app), 8);
+}
+
+/// This test checks that a seeded AIRng makes random selection reproducible, even though the
+/// entities make their decisions in parallel.
+#[test]
+fn parallel_decisions_are_reproducible_with_seed() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let run = || {
+        let mut app = test_app();
+        app.add_plugin(UtilityAIPlugin);
+        app.insert_resource(AIRng::seeded(7));
+
+        DefineAI::<AI>::new()
+            .set_selection_strategy(SelectionStrategy::WeightedRandomTopN(2))
+            .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+            .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+            .register(&mut app);
+
+        let entity_ids: Vec<Entity> = (0..64)
+            .map(|_| {
+                app.world
+                    .spawn((
+                        SomeData { val: 0.5 },
+                        SomeOtherData { val: 0.5 },
+                        AI {},
+                        AIMeta::new::<AI>(),
+                    ))
+                    .id()
+            })
+            .collect();
+
+        app.update();
+
+        entity_ids
+            .iter()
+            .map(|&entity_id| app.world.get::<AIMeta>(entity_id).unwrap().current_action)
+            .collect::<Vec<Option<TypeId>>>()
+    };
+
+    let actions = run();
+    assert_eq!(actions, run());
+    assert!(actions.contains(&Some(TypeId::of::<ActionOne>())));
+    assert!(actions.contains(&Some(TypeId::of::<ActionTwo>())));
+}
+
+impl AIPosition for Position {
+    fn ai_position(&self) -> Vec3 {
+        self.val.extend(0.0)
+    }
+}
+
+/// This test checks that spatial filters restrict targeted inputs to the targets near the
+/// subject, after the decision's filter sets have been applied.
+#[test]
+fn spatial_filters_only_score_nearby_targets() {
+    // SETUP
+    #[targeted_input_system]
+    fn targeted_utility_input_1(subject: (&Position,), target: (&Position,)) -> f32 {
+        subject.0.val.distance(target.0.val)
+    }
+
+    #[targeted_input_system]
+    fn targeted_utility_input_2(subject: (&Position,), target: (&Position,)) -> f32 {
+        subject.0.val.distance(target.0.val)
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+    app.add_ai_spatial_index::<Position>(5.0);
+
+    DefineAI::<AI1>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::targeted_filter::<AA>(),
+            Consideration::targeted(targeted_utility_input_1).with_max_distance(10.0),
+        ])
+        .register(&mut app);
+
+    DefineAI::<AI2>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::targeted_filter::<AA>(),
+            Consideration::targeted(targeted_utility_input_2).with_nearest_targets(2),
+        ])
+        .register(&mut app);
+
+    let position = |x: f32| Position {
+        val: Vec2::new(x, 0.0),
+    };
+    let subject_1 = app
+        .world
+        .spawn((position(0.0), AI1 {}, AIMeta::new::<AI1>()))
+        .id();
+    let subject_2 = app
+        .world
+        .spawn((position(0.0), AI2 {}, AIMeta::new::<AI2>()))
+        .id();
+    let near = app.world.spawn((position(3.0), AA {})).id();
+    let middle = app.world.spawn((position(-8.0), AA {})).id();
+    let _far = app.world.spawn((position(40.0), AA {})).id();
+    let _not_a_target = app.world.spawn((position(1.0),)).id();
+
+    app.update();
+
+    let scored_targets = |app: &bevy::prelude::App, subject: Entity, input: InputId| {
+        let ai_meta = app.world.get::<AIMeta>(subject).unwrap();
+        let mut targets: Vec<Entity> = ai_meta.targeted_input_scores[&input]
+            .keys()
+            .copied()
+            .collect();
+        targets.sort();
+        targets
+    };
+
+    assert_eq!(
+        scored_targets(&app, subject_1, InputId::of(targeted_utility_input_1)),
+        vec![near, middle]
+    );
+    assert_eq!(
+        scored_targets(&app, subject_2, InputId::of(targeted_utility_input_2)),
+        vec![near, middle]
+    );
+
+    // targets that move out of range are no longer scored
+    app.world.get_mut::<Position>(middle).unwrap().val = Vec2::new(-30.0, 0.0);
+    app.update();
+
+    assert_eq!(
+        scored_targets(&app, subject_1, InputId::of(targeted_utility_input_1)),
+        vec![near]
+    );
+    assert_eq!(
+        scored_targets(&app, subject_2, InputId::of(targeted_utility_input_2)),
+        vec![near, middle]
+    );
+}
+
+/// This test checks that cached inputs are only recalculated when the components they read
+/// change, or once the cached score reaches its max age.
+#[test]
+fn cached_inputs_recalculate_on_change_or_max_age() {
+    // SETUP
+    static SIMPLE_CALCULATIONS: AtomicUsize = AtomicUsize::new(0);
+    static TARGETED_CALCULATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    #[input_system]
+    fn utility_input_1(some_data: &SomeData) -> f32 {
+        SIMPLE_CALCULATIONS.fetch_add(1, Ordering::SeqCst);
+        some_data.val
+    }
+
+    #[targeted_input_system]
+    fn targeted_utility_input_1(target: (&SomeOtherData,)) -> f32 {
+        TARGETED_CALCULATIONS.fetch_add(1, Ordering::SeqCst);
+        target.0.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_1).with_cache(2)])
+        .add_decision::<ActionTwo>(vec![
+            Consideration::targeted(targeted_utility_input_1).with_cache(100)
+        ])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((SomeData { val: 0.25 }, AI {}, AIMeta::new::<AI>()))
+        .id();
+    let target_1 = app.world.spawn(SomeOtherData { val: 0.5 }).id();
+    let _target_2 = app.world.spawn(SomeOtherData { val: 0.75 }).id();
+
+    let calculations = || {
+        (
+            SIMPLE_CALCULATIONS.load(Ordering::SeqCst),
+            TARGETED_CALCULATIONS.load(Ordering::SeqCst),
+        )
+    };
+
+    app.update();
+    assert_eq!(calculations(), (1, 2));
+
+    // nothing has changed so the cached scores are reused
+    app.update();
+    assert_eq!(calculations(), (1, 2));
+
+    // only the changed target is recalculated
+    app.world.get_mut::<SomeData>(entity_id).unwrap().val = 0.5;
+    app.world.get_mut::<SomeOtherData>(target_1).unwrap().val = 0.25;
+    app.update();
+    assert_eq!(calculations(), (2, 3));
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.input_scores[&InputId::of(utility_input_1)], 0.5);
+    assert_eq!(
+        ai_meta.targeted_input_scores[&InputId::of(targeted_utility_input_1)][&target_1],
+        0.25
+    );
+
+    // the simple score is reused twice before it is recalculated
+    app.update();
+    app.update();
+    assert_eq!(calculations(), (2, 3));
+    app.update();
+    assert_eq!(calculations(), (3, 3));
+}
+
+/// This test checks that a target selector restricts a decision to the targets it selects,
+/// and that it combines with the decision's filters.
+#[test]
+fn target_selector_supplies_decision_targets() {
+    // SETUP
+    #[target_selector]
+    fn richer_targets(
+        subject: &SomeOtherData,
+        q_targets: Query<(Entity, &SomeData)>,
+    ) -> Vec<Entity> {
+        q_targets
+            .iter()
+            .filter(|(_, some_data)| some_data.val > subject.val)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    #[targeted_input_system]
+    fn targeted_utility_input(target: &SomeData) -> f32 {
+        target.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::target_selector(richer_targets),
+            Consideration::targeted(targeted_utility_input),
+            Consideration::targeted_exclude_filter::<BB>(),
+        ])
+        .register(&mut app);
+
+    let subject = app
+        .world
+        .spawn((SomeOtherData { val: 0.5 }, AI {}, AIMeta::new::<AI>()))
+        .id();
+    let poor = app.world.spawn(SomeData { val: 0.25 }).id();
+    let rich = app.world.spawn(SomeData { val: 0.75 }).id();
+    let richest_excluded = app.world.spawn((SomeData { val: 1.0 }, BB {})).id();
+
+    app.update();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(subject).unwrap();
+    let mut selected = ai_meta.selected_targets[&InputId::of(richer_targets)].clone();
+    selected.sort();
+    let mut expected = vec![rich, richest_excluded];
+    expected.sort();
+    assert_eq!(selected, expected);
+
+    let score_map = &ai_meta.targeted_input_scores[&InputId::of(targeted_utility_input)];
+    assert_eq!(
+        score_map.keys().copied().collect::<Vec<Entity>>(),
+        vec![rich]
+    );
+    assert_eq!(ai_meta.current_target, Some(rich));
+
+    // targets that are no longer selected are no longer scored
+    app.world.get_mut::<SomeData>(rich).unwrap().val = 0.0;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(subject).unwrap();
+    assert!(ai_meta.targeted_input_scores[&InputId::of(targeted_utility_input)].is_empty());
+    assert!(!ai_meta.selected_targets[&InputId::of(richer_targets)].contains(&poor));
+}
+
+/// This test checks that an action factory builds the action from the decision context, and
+/// rebuilds it when the target changes.
+#[test]
+fn action_factory_builds_action_from_decision_context() {
+    // SETUP
+    #[targeted_input_system]
+    fn targeted_utility_input(target: (&Position, &SomeData)) -> f32 {
+        target.1.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision_with_factory::<ActionMoveTo>(
+            vec![Consideration::targeted(targeted_utility_input)],
+            |context| {
+                // the factory runs with all of the world's resources available
+                assert!(context.world.contains_resource::<AppTypeRegistry>());
+                ActionMoveTo {
+                    destination: context
+                        .world
+                        .get::<Position>(context.target.unwrap())
+                        .unwrap()
+                        .val,
+                    score: context.score,
+                    input_score: context
+                        .input_score(InputId::of(targeted_utility_input))
+                        .unwrap(),
+                }
+            },
+        )
+        .register(&mut app);
+
+    let subject = app.world.spawn((AI {}, AIMeta::new::<AI>())).id();
+    let target_1 = app
+        .world
+        .spawn((
+            Position {
+                val: Vec2::new(1.0, 2.0),
+            },
+            SomeData { val: 0.75 },
+        ))
+        .id();
+    let target_2 = app
+        .world
+        .spawn((
+            Position {
+                val: Vec2::new(3.0, 4.0),
+            },
+            SomeData { val: 0.5 },
+        ))
+        .id();
+
+    app.update();
+
+    let action = app.world.get::<ActionMoveTo>(subject).unwrap();
+    assert_eq!(action.destination, Vec2::new(1.0, 2.0));
+    assert_eq!(action.score, 0.75);
+    assert_eq!(action.input_score, 0.75);
+    assert_eq!(
+        app.world.get::<ActionStatus>(subject),
+        Some(&ActionStatus::Running)
+    );
+
+    // the action is rebuilt for the new target
+    app.world.get_mut::<SomeData>(target_1).unwrap().val = 0.25;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(subject).unwrap();
+    assert_eq!(ai_meta.current_target, Some(target_2));
+    let action = app.world.get::<ActionMoveTo>(subject).unwrap();
+    assert_eq!(action.destination, Vec2::new(3.0, 4.0));
+    assert_eq!(action.score, 0.5);
+    assert_eq!(action.input_score, 0.5);
+}
+
+/// This test checks that an action factory must build the decision's action.
+#[test]
+#[should_panic(expected = "The action factory of a decision must build its action")]
+fn action_factory_must_build_decision_action() {
+    #[input_system]
+    fn utility_input(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    DefineAI::<AI>::new().add_decision_with_settings::<ActionOne>(
+        vec![Consideration::simple(utility_input)],
+        DecisionSettings::default().with_action_factory(|_| ActionTwo {}),
+    );
+}
+
+/// This test checks that a multi targeted decision picks the best combination of targets and
+/// that the chosen set is carried by ActionTargets.
+#[test]
+fn multi_targeted_decision_picks_best_combination_of_targets() {
+    // SETUP
+    #[multi_targeted_input_system]
+    fn trade_margin(targets: (&SomeData, &SomeOtherData)) -> Option<f32> {
+        let (buyer, seller) = targets;
+        if buyer.val < seller.val {
+            return None;
+        }
+        Some(buyer.val - seller.val)
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::multi_targeted(trade_margin)])
+        .register(&mut app);
+
+    let subject = app
+        .world
+        .spawn((AI {}, AIMeta::new::<AI>(), AIDecisionHistory::new(8)))
+        .id();
+    let buyer_1 = app.world.spawn(SomeData { val: 0.75 }).id();
+    let buyer_2 = app.world.spawn(SomeData { val: 0.5 }).id();
+    let seller_1 = app.world.spawn(SomeOtherData { val: 0.25 }).id();
+    let seller_2 = app.world.spawn(SomeOtherData { val: 0.6 }).id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(subject).unwrap();
+    let score_map = &ai_meta.multi_targeted_input_scores[&InputId::of(trade_margin)];
+    // buyer 2 can't afford seller 2
+    assert_eq!(score_map.len(), 3);
+    assert!(!score_map.contains_key(&vec![buyer_2, seller_2]));
+    assert_eq!(ai_meta.current_targets, vec![buyer_1, seller_1]);
+    assert_eq!(ai_meta.current_target, Some(buyer_1));
+    assert_eq!(
+        app.world.get::<ActionTargets>(subject),
+        Some(&ActionTargets {
+            targets: vec![buyer_1, seller_1]
+        })
+    );
+    assert_eq!(
+        app.world.get::<ActionTarget>(subject).unwrap().target,
+        buyer_1
+    );
+
+    let history = app.world.get::<AIDecisionHistory>(subject).unwrap();
+    let record = history.records().last().unwrap();
+    assert_eq!(record.targets, vec![buyer_1, seller_1]);
+    assert_eq!(record.scores[0].targets, vec![buyer_1, seller_1]);
+
+    // switching the seller keeps the primary target but changes the set of targets
+    let mut target_reader = app.world.resource::<Events<TargetChanged>>().get_reader();
+    app.world.get_mut::<SomeOtherData>(seller_2).unwrap().val = 0.0;
+    app.update();
+
+    let target_changed: Vec<TargetChanged> = target_reader
+        .iter(app.world.resource::<Events<TargetChanged>>())
+        .cloned()
+        .collect();
+    assert_eq!(target_changed.len(), 1);
+    assert_eq!(target_changed[0].old_targets, vec![buyer_1, seller_1]);
+    assert_eq!(target_changed[0].new_targets, vec![buyer_1, seller_2]);
+
+    assert_eq!(
+        app.world.get::<ActionTargets>(subject),
+        Some(&ActionTargets {
+            targets: vec![buyer_1, seller_2]
+        })
+    );
+    assert_eq!(
+        app.world.get::<ActionTarget>(subject).unwrap().target,
+        buyer_1
+    );
+}
+
+/// This test checks that filters applied to one of the targets of a multi targeted input restrict
+/// the combinations of targets that are scored.
+#[test]
+fn multi_targeted_decision_filters_each_target() {
+    // SETUP
+    #[multi_targeted_input_system]
+    fn trade_margin(targets: (&SomeData, &SomeOtherData)) -> f32 {
+        let (buyer, seller) = targets;
+        buyer.val - seller.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::multi_targeted(trade_margin),
+            Consideration::targeted_filter::<AA>().for_target(0),
+            Consideration::targeted_exclude_filter::<BB>().for_target(1),
+        ])
+        .register(&mut app);
+
+    let subject = app.world.spawn((AI {}, AIMeta::new::<AI>())).id();
+    let buyer_1 = app.world.spawn((SomeData { val: 0.5 }, AA {})).id();
+    app.world.spawn(SomeData { val: 1.0 });
+    let seller_1 = app.world.spawn(SomeOtherData { val: 0.25 }).id();
+    app.world.spawn((SomeOtherData { val: 0.0 }, BB {}));
+
+    // TEST
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(subject).unwrap();
+    let score_map = &ai_meta.multi_targeted_input_scores[&InputId::of(trade_margin)];
+    assert_eq!(score_map.len(), 1);
+    assert_eq!(ai_meta.current_targets, vec![buyer_1, seller_1]);
+}
+
+/// This test checks that a decision with multi targeted considerations can't have filters that
+/// don't say which target they apply to.
+#[test]
+#[should_panic(expected = "Targeted filters must use Consideration::for_target")]
+fn multi_targeted_decision_filters_must_have_target() {
+    #[multi_targeted_input_system]
+    fn trade_margin(targets: (&SomeData, &SomeOtherData)) -> f32 {
+        targets.0.val - targets.1.val
+    }
+
+    DefineAI::<AI>::new().add_decision::<ActionOne>(vec![
+        Consideration::multi_targeted(trade_margin),
+        Consideration::targeted_filter::<AA>(),
+    ]);
+}
+
+/// This test checks that decisions can't give a targeted input different filters, as its scores
+/// are shared by every decision of the AI.
+#[test]
+#[should_panic(expected = "has different filters in different decisions")]
+fn targeted_input_must_have_same_filters_in_every_decision() {
+    #[targeted_input_system]
+    fn targeted_utility_input(target: &SomeData) -> f32 {
+        target.val
+    }
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::targeted(targeted_utility_input),
+            Consideration::targeted_filter::<AA>(),
+        ])
+        .add_decision::<ActionTwo>(vec![Consideration::targeted(targeted_utility_input)]);
+}
+
+/// This test checks that decisions can give a targeted input the same filters in any order.
+#[test]
+fn targeted_input_can_have_same_filters_in_every_decision() {
+    #[targeted_input_system]
+    fn targeted_utility_input(target: &SomeData) -> f32 {
+        target.val
+    }
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![
+            Consideration::targeted(targeted_utility_input),
+            Consideration::targeted_filter::<AA>(),
+            Consideration::targeted_filter::<BB>(),
+        ])
+        .add_decision::<ActionTwo>(vec![
+            Consideration::targeted_filter::<BB>(),
+            Consideration::targeted_filter::<AA>(),
+            Consideration::targeted(targeted_utility_input),
+        ]);
+}
+
+/// This test checks that a decision can't mix targeted and multi targeted considerations.
+#[test]
+#[should_panic(expected = "Consideration::multi_targeted considerations are invalid")]
+fn multi_targeted_decision_cannot_mix_targeted_considerations() {
+    #[multi_targeted_input_system]
+    fn trade_margin(targets: (&SomeData, &SomeOtherData)) -> f32 {
+        targets.0.val - targets.1.val
+    }
+
+    #[targeted_input_system]
+    fn targeted_utility_input(target: &SomeData) -> f32 {
+        target.val
+    }
+
+    DefineAI::<AI>::new().add_decision::<ActionOne>(vec![
+        Consideration::multi_targeted(trade_margin),
+        Consideration::targeted(targeted_utility_input),
+    ]);
+}
+
+/// This test checks that decisions are picked from the highest priority bucket that has a
+/// viable decision, even if a lower priority bucket has a higher scoring decision.
+#[test]
+fn decision_buckets_pick_from_highest_priority_viable_bucket() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_bucket("Survival", 1.0)
+        .add_decision_with_settings::<ActionOne>(
+            vec![Consideration::simple(utility_input_low)],
+            DecisionSettings::default().with_bucket("Survival"),
+        )
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.75 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    // the survival bucket has no viable decision, so the lower priority decision is picked
+    app.world.get_mut::<SomeData>(entity_id).unwrap().val = 0.0;
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+}
+
+/// This test checks that a decision can only be put in a bucket that has been added.
+#[test]
+#[should_panic(expected = "is in the bucket 'Survival' which hasn't been added")]
+fn decision_buckets_must_be_added() {
+    #[input_system]
+    fn utility_input(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision_with_settings::<ActionOne>(
+            vec![Consideration::simple(utility_input)],
+            DecisionSettings::default().with_bucket("Survival"),
+        )
+        .register(&mut app);
+}
+
+/// This test checks that swapping an entity's marker component swaps its AI, ending the action
+/// of the old AI, and that removing the marker component removes the AI.
+#[test]
+fn swapping_marker_component_swaps_ai() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI1>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .register(&mut app);
+    DefineAI::<AI2>::new()
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_low)])
+        .register(&mut app);
+
+    let entity_id = app.world.spawn((SomeData { val: 0.5 }, AI1 {})).id();
+
+    app.update();
+    app.update();
+
+    assert!(app.world.get::<ActionOne>(entity_id).is_some());
+
+    let mut ended_reader = app.world.resource::<Events<ActionEnded>>().get_reader();
+
+    // swap the marker component
+    app.world
+        .entity_mut(entity_id)
+        .remove::<AI1>()
+        .insert(AI2 {});
+    app.update();
+    app.update();
+
+    let ended: Vec<ActionEnded> = ended_reader
+        .iter(app.world.resource::<Events<ActionEnded>>())
+        .cloned()
+        .collect();
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].action, TypeId::of::<ActionOne>());
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.ai_definition, TypeId::of::<AI2>());
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+    assert!(app.world.get::<ActionOne>(entity_id).is_none());
+    assert!(app.world.get::<ActionTwo>(entity_id).is_some());
+
+    // remove the AI
+    app.world.entity_mut(entity_id).remove::<AI2>();
+    app.update();
+
+    assert!(app.world.get::<AIMeta>(entity_id).is_none());
+    assert!(app.world.get::<ActionTwo>(entity_id).is_none());
+    assert!(app.world.get::<ActionStatus>(entity_id).is_none());
+}
+
+/// This test checks that the decisions of a registered AI can be removed and added while the
+/// app runs.
+#[test]
+fn ai_definition_can_be_edited_at_runtime() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.75 },
+            SomeOtherData { val: 0.25 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    DefineAI::<AI>::edit(&app.world)
+        .remove_decision::<ActionOne>()
+        .update(&mut app.world);
+    app.update();
+
+    let ai_definitions = app.world.resource::<AIDefinitions>();
+    let required_inputs = &ai_definitions.map[&TypeId::of::<AI>()].required_inputs;
+    assert!(!required_inputs.contains(&InputId::of(utility_input_low)));
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionTwo>()));
+
+    DefineAI::<AI>::edit(&app.world)
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .update(&mut app.world);
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+}
+
+/// This test checks that the registered AI is kept until the edit is applied, so that
+/// abandoning an edit doesn't lose the AI.
+#[test]
+fn ai_definition_is_kept_until_edit_is_updated() {
+    // SETUP
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .register(&mut app);
+
+    let entity_id = app
+        .world
+        .spawn((
+            SomeData { val: 0.75 },
+            SomeOtherData { val: 0.25 },
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    // TEST
+    let edit = DefineAI::<AI>::edit(&app.world).remove_decision::<ActionOne>();
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+
+    drop(edit);
+    app.update();
+
+    let ai_definitions = app.world.resource::<AIDefinitions>();
+    assert_eq!(ai_definitions.map[&TypeId::of::<AI>()].decisions.len(), 2);
+    let ai_meta = app.world.get::<AIMeta>(entity_id).unwrap();
+    assert_eq!(ai_meta.current_action, Some(TypeId::of::<ActionOne>()));
+}
+
+/// This test checks that an AI can't be updated with an input that hasn't been added to the App.
+#[test]
+#[should_panic(expected = "hasn't been added to the App")]
+fn ai_definition_update_requires_added_inputs() {
+    #[input_system]
+    fn utility_input_low(some_data: &SomeData) -> f32 {
+        some_data.val
+    }
+
+    #[input_system]
+    fn utility_input_high(some_other_data: &SomeOtherData) -> f32 {
+        some_other_data.val
+    }
+
+    let mut app = test_app();
+    app.add_plugin(UtilityAIPlugin);
+
+    DefineAI::<AI>::new()
+        .add_decision::<ActionOne>(vec![Consideration::simple(utility_input_low)])
+        .register(&mut app);
+
+    DefineAI::<AI>::edit(&app.world)
+        .add_decision::<ActionTwo>(vec![Consideration::simple(utility_input_high)])
+        .update(&mut app.world);
+}
@@ -1,10 +1,12 @@
 mod common;
 
+use bevy::core::TaskPoolPlugin;
+use bevy::prelude::{Entity, Res, Resource, With, Without};
 use bevy::utils::hashbrown::HashSet;
 use bevy::{app::App, utils::HashMap};
-use bevy_utility_ai::{AIDefinition, AIDefinitions, AIMeta};
+use bevy_utility_ai::{AIDefinition, AIDefinitions, AIMeta, InputId};
 use bevy_utility_ai_macros::input_system;
-use common::{SomeData, AI};
+use common::{SomeData, SomeOtherData, AA, AI, BB};
 use std::any::TypeId;
 
 #[test]
@@ -28,6 +30,7 @@ fn input_system_macro_updates_aimeta_inputs() {
 
     let mut app = App::new();
 
+    app.add_plugin(TaskPoolPlugin::default());
     app.add_system(utility_input_low);
 
     app.init_resource::<AIDefinitions>();
@@ -37,8 +40,9 @@ fn input_system_macro_updates_aimeta_inputs() {
         TypeId::of::<AI>(),
         AIDefinition {
             decisions: vec![], // this field doesn't matter for this test
-            required_inputs: HashSet::from_iter(vec![utility_input_low as usize]),
+            required_inputs: HashSet::from_iter(vec![InputId::of(utility_input_low)]),
             targeted_input_filter_sets: HashMap::new(),
+            ..Default::default()
         },
     );
 
@@ -53,6 +57,94 @@ fn input_system_macro_updates_aimeta_inputs() {
 
     assert!(ai_meta
         .input_scores
-        .contains_key(&(utility_input_low as usize)));
-    assert_eq!(ai_meta.input_scores[&(utility_input_low as usize)], 0.25);
+        .contains_key(&InputId::of(utility_input_low)));
+    assert_eq!(ai_meta.input_scores[&InputId::of(utility_input_low)], 0.25);
+}
+
+#[derive(Resource)]
+struct Multiplier(f32);
+
+#[test]
+fn input_system_macro_supports_optional_components_entities_filters_and_resources() {
+    #[input_system]
+    fn utility_input(
+        entity: Entity,
+        some_data: &SomeData,
+        some_other_data: Option<&SomeOtherData>,
+        _: With<AA>,
+        _: Without<BB>,
+        multiplier: Res<Multiplier>,
+    ) -> f32 {
+        let other_val = some_other_data.map_or(0.0, |some_other_data| some_other_data.val);
+        (some_data.val + other_val) * multiplier.0 + entity.index() as f32
+    }
+
+    let mut app = App::new();
+
+    app.add_plugin(TaskPoolPlugin::default());
+    app.add_system(utility_input);
+
+    app.init_resource::<AIDefinitions>();
+    app.insert_resource(Multiplier(2.0));
+
+    let mut ai_definitions = app.world.resource_mut::<AIDefinitions>();
+    ai_definitions.map.insert(
+        TypeId::of::<AI>(),
+        AIDefinition {
+            decisions: vec![], // this field doesn't matter for this test
+            required_inputs: HashSet::from_iter(vec![InputId::of(utility_input)]),
+            ..Default::default()
+        },
+    );
+
+    let entity_without_other = app
+        .world
+        .spawn((SomeData { val: 0.25 }, AA {}, AI {}, AIMeta::new::<AI>()))
+        .id();
+    let entity_with_other = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            SomeOtherData { val: 0.125 },
+            AA {},
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+    let entity_not_with = app
+        .world
+        .spawn((SomeData { val: 0.25 }, AI {}, AIMeta::new::<AI>()))
+        .id();
+    let entity_without = app
+        .world
+        .spawn((
+            SomeData { val: 0.25 },
+            AA {},
+            BB {},
+            AI {},
+            AIMeta::new::<AI>(),
+        ))
+        .id();
+
+    app.update();
+
+    let score = |entity_id: Entity| {
+        app.world
+            .get::<AIMeta>(entity_id)
+            .unwrap()
+            .input_scores
+            .get(&InputId::of(utility_input))
+            .copied()
+    };
+
+    assert_eq!(
+        score(entity_without_other),
+        Some(0.5 + entity_without_other.index() as f32)
+    );
+    assert_eq!(
+        score(entity_with_other),
+        Some(0.75 + entity_with_other.index() as f32)
+    );
+    assert_eq!(score(entity_not_with), None);
+    assert_eq!(score(entity_without), None);
 }
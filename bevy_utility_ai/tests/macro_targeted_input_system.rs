@@ -1,15 +1,20 @@
 mod common;
 
 use crate::common::SomeOtherData;
+use bevy::core::TaskPoolPlugin;
+use bevy::prelude::{Res, Resource};
 use bevy::utils::hashbrown::HashSet;
 use bevy::{app::App, utils::HashMap};
-use bevy_utility_ai::{AIDefinition, AIDefinitions, AIMeta, AITargetEntitySets};
+use bevy_utility_ai::{
+    AIDefinition, AIDefinitions, AIMeta, AITargetEntitySets, InputId, TargetFilter,
+};
 use bevy_utility_ai_macros::targeted_input_system;
 use common::{SomeData, AI};
 use std::any::TypeId;
 
 fn test_app() -> App {
     let mut app = App::new();
+    app.add_plugin(TaskPoolPlugin::default());
     app.init_resource::<AIDefinitions>();
     app.init_resource::<AITargetEntitySets>();
     app
@@ -37,6 +42,17 @@ fn targeted_input_system_produces_valid_system() {
     app.add_system(targeted_input);
 }
 
+#[test]
+fn targeted_input_system_without_tuples_produces_valid_system() {
+    #[targeted_input_system]
+    fn targeted_input(subject: &SomeOtherData, target: &SomeData) -> f32 {
+        subject.val - target.val
+    }
+
+    let mut app = App::new();
+    app.add_system(targeted_input);
+}
+
 #[test]
 fn simple_targeted_input_system_updates_aimeta_inputs() {
     #[targeted_input_system]
@@ -53,8 +69,9 @@ fn simple_targeted_input_system_updates_aimeta_inputs() {
         TypeId::of::<AI>(),
         AIDefinition {
             decisions: vec![], // this field doesn't matter for this test
-            required_inputs: HashSet::from_iter(vec![simple_targeted_input as usize]),
+            required_inputs: HashSet::from_iter(vec![InputId::of(simple_targeted_input)]),
             targeted_input_filter_sets: HashMap::new(),
+            ..Default::default()
         },
     );
 
@@ -68,9 +85,9 @@ fn simple_targeted_input_system_updates_aimeta_inputs() {
     assert_eq!(ai_meta.targeted_input_scores.len(), 1);
     assert!(ai_meta
         .targeted_input_scores
-        .contains_key(&(simple_targeted_input as usize)));
+        .contains_key(&InputId::of(simple_targeted_input)));
     assert_eq!(
-        ai_meta.targeted_input_scores[&(simple_targeted_input as usize)][&target_entity_id],
+        ai_meta.targeted_input_scores[&InputId::of(simple_targeted_input)][&target_entity_id],
         0.25
     );
 }
@@ -91,8 +108,9 @@ fn targeted_input_system_updates_aimeta_inputs() {
         TypeId::of::<AI>(),
         AIDefinition {
             decisions: vec![], // this field doesn't matter for this test
-            required_inputs: HashSet::from_iter(vec![targeted_input as usize]),
+            required_inputs: HashSet::from_iter(vec![InputId::of(targeted_input)]),
             targeted_input_filter_sets: HashMap::new(),
+            ..Default::default()
         },
     );
 
@@ -109,9 +127,9 @@ fn targeted_input_system_updates_aimeta_inputs() {
     assert_eq!(ai_meta.targeted_input_scores.len(), 1);
     assert!(ai_meta
         .targeted_input_scores
-        .contains_key(&(targeted_input as usize)));
+        .contains_key(&InputId::of(targeted_input)));
     assert_eq!(
-        ai_meta.targeted_input_scores[&(targeted_input as usize)][&target_entity_id],
+        ai_meta.targeted_input_scores[&InputId::of(targeted_input)][&target_entity_id],
         0.5
     );
 }
@@ -131,11 +149,15 @@ fn simple_targeted_input_system_respects_filter_set() {
         TypeId::of::<AI>(),
         AIDefinition {
             decisions: vec![], // this field doesn't matter for this test
-            required_inputs: HashSet::from_iter(vec![simple_targeted_input as usize]),
+            required_inputs: HashSet::from_iter(vec![InputId::of(simple_targeted_input)]),
             targeted_input_filter_sets: HashMap::from_iter(vec![(
-                simple_targeted_input as usize,
-                vec![1],
+                InputId::of(simple_targeted_input),
+                TargetFilter {
+                    include: vec![InputId::from_name("set")],
+                    ..Default::default()
+                },
             )]),
+            ..Default::default()
         },
     );
 
@@ -145,7 +167,7 @@ fn simple_targeted_input_system_respects_filter_set() {
     let entity_target = app.world.spawn(SomeData { val: 0.75 }).id();
 
     let mut ai_target_entity_sets = app.world.resource_mut::<AITargetEntitySets>();
-    ai_target_entity_sets.insert(1, entity_target);
+    ai_target_entity_sets.insert(InputId::from_name("set"), entity_target);
 
     app.update();
 
@@ -154,13 +176,138 @@ fn simple_targeted_input_system_respects_filter_set() {
     assert_eq!(ai_meta.targeted_input_scores.len(), 1);
     assert!(ai_meta
         .targeted_input_scores
-        .contains_key(&(simple_targeted_input as usize)));
+        .contains_key(&InputId::of(simple_targeted_input)));
     assert_eq!(
-        ai_meta.targeted_input_scores[&(simple_targeted_input as usize)][&entity_target],
+        ai_meta.targeted_input_scores[&InputId::of(simple_targeted_input)][&entity_target],
         0.75
     );
     assert!(
-        !ai_meta.targeted_input_scores[&(simple_targeted_input as usize)]
+        !ai_meta.targeted_input_scores[&InputId::of(simple_targeted_input)]
             .contains_key(&entity_ignore)
     );
 }
+
+#[test]
+fn simple_targeted_input_system_respects_exclude_filter_set() {
+    #[targeted_input_system]
+    fn simple_targeted_input(target: (&SomeData,)) -> f32 {
+        target.0.val
+    }
+
+    let mut app = test_app();
+    app.add_system(simple_targeted_input);
+
+    let mut ai_definitions = app.world.resource_mut::<AIDefinitions>();
+    ai_definitions.map.insert(
+        TypeId::of::<AI>(),
+        AIDefinition {
+            decisions: vec![], // this field doesn't matter for this test
+            required_inputs: HashSet::from_iter(vec![InputId::of(simple_targeted_input)]),
+            targeted_input_filter_sets: HashMap::from_iter(vec![(
+                InputId::of(simple_targeted_input),
+                TargetFilter {
+                    exclude: vec![InputId::from_name("set")],
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        },
+    );
+
+    // spawn some entities
+    let entity_subject = app.world.spawn((AI {}, AIMeta::new::<AI>())).id();
+    let entity_ignore = app.world.spawn(SomeData { val: 0.25 }).id();
+    let entity_target = app.world.spawn(SomeData { val: 0.75 }).id();
+
+    let mut ai_target_entity_sets = app.world.resource_mut::<AITargetEntitySets>();
+    ai_target_entity_sets.insert(InputId::from_name("set"), entity_ignore);
+
+    app.update();
+
+    let ai_meta = app.world.get::<AIMeta>(entity_subject).unwrap();
+
+    assert_eq!(
+        ai_meta.targeted_input_scores[&InputId::of(simple_targeted_input)][&entity_target],
+        0.75
+    );
+    assert!(
+        !ai_meta.targeted_input_scores[&InputId::of(simple_targeted_input)]
+            .contains_key(&entity_ignore)
+    );
+}
+
+#[derive(Resource)]
+struct Multiplier(f32);
+
+#[test]
+fn targeted_input_system_supports_optional_components_entities_and_resources() {
+    #[targeted_input_system]
+    fn targeted_input(
+        subject: (Entity, &SomeOtherData),
+        target: (Entity, &SomeData, Option<&SomeOtherData>),
+        multiplier: Res<Multiplier>,
+    ) -> Option<f32> {
+        let (subject_entity, subject_data) = subject;
+        let (target_entity, some_data, some_other_data) = target;
+        assert_ne!(subject_entity, target_entity);
+        // targets with a negative value are invalid
+        if some_data.val < 0.0 {
+            return None;
+        }
+        let other_val = some_other_data.map_or(0.0, |some_other_data| some_other_data.val);
+        Some((subject_data.val + some_data.val + other_val) * multiplier.0)
+    }
+
+    let mut app = test_app();
+
+    app.add_system(targeted_input);
+    app.insert_resource(Multiplier(2.0));
+
+    let mut ai_definitions = app.world.resource_mut::<AIDefinitions>();
+    ai_definitions.map.insert(
+        TypeId::of::<AI>(),
+        AIDefinition {
+            decisions: vec![], // this field doesn't matter for this test
+            required_inputs: HashSet::from_iter(vec![InputId::of(targeted_input)]),
+            ..Default::default()
+        },
+    );
+
+    let subject_entity_id = app
+        .world
+        .spawn((SomeOtherData { val: 0.5 }, AI {}, AIMeta::new::<AI>()))
+        .id();
+    let target_without_other = app.world.spawn(SomeData { val: 0.25 }).id();
+    let target_with_other = app
+        .world
+        .spawn((SomeData { val: 0.25 }, SomeOtherData { val: 0.125 }))
+        .id();
+    let invalid_target = app.world.spawn(SomeData { val: -1.0 }).id();
+
+    app.update();
+
+    let scores = |app: &App| {
+        app.world
+            .get::<AIMeta>(subject_entity_id)
+            .unwrap()
+            .targeted_input_scores[&InputId::of(targeted_input)]
+            .clone()
+    };
+
+    let score_map = scores(&app);
+    assert_eq!(score_map.len(), 2);
+    assert_eq!(score_map[&target_without_other], 1.5);
+    assert_eq!(score_map[&target_with_other], 1.75);
+    assert!(!score_map.contains_key(&invalid_target));
+
+    // a target that becomes invalid has its previous score removed
+    app.world
+        .get_mut::<SomeData>(target_with_other)
+        .unwrap()
+        .val = -1.0;
+    app.update();
+
+    let score_map = scores(&app);
+    assert_eq!(score_map.len(), 1);
+    assert!(!score_map.contains_key(&target_with_other));
+}
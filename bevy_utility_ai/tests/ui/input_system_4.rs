@@ -0,0 +1,8 @@
+use bevy_utility_ai_macros::input_system;
+
+#[input_system]
+fn utility_input(some_data: &SomeData, multiplier: ResMut<Multiplier>) -> f32 {
+    some_data.val * multiplier.0
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use bevy_utility_ai_macros::input_system;
+
+#[input_system(id = "utility_input")]
+fn utility_input(some_data: &SomeData) -> f32 {
+    some_data.val
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use bevy_utility_ai_macros::multi_targeted_input_system;
+
+#[multi_targeted_input_system]
+fn trade(targets: &SomeData) -> f32 {
+    targets.val
+}
+
+fn main() {}
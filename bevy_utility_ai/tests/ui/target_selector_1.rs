@@ -0,0 +1,8 @@
+use bevy_utility_ai_macros::target_selector;
+
+#[target_selector]
+fn selector(subject: (&SomeData,)) -> Entity {
+    subject.0.target
+}
+
+fn main() {}
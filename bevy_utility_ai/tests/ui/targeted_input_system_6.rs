@@ -0,0 +1,8 @@
+use bevy_utility_ai_macros::targeted_input_system;
+
+#[targeted_input_system]
+fn simple_targeted_input(target: (&SomeData,), multiplier: ResMut<Multiplier>) -> f32 {
+    target.0.val * multiplier.0
+}
+
+fn main() {}
@@ -1,87 +1,211 @@
+use crate::params::{
+    call_body, generic_arg, parse_resource, skip_subject, InputName, QueryParam,
+};
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{Error, FnArg, ItemFn};
+use quote::{format_ident, quote, ToTokens};
+use syn::__private::TokenStream2;
+use syn::{Error, FnArg, Ident, ItemFn, ReturnType, Type};
 
-pub(crate) fn input_system(_: TokenStream, input: TokenStream) -> Result<TokenStream, Error> {
-    let item_fn = match syn::parse::<ItemFn>(input) {
-        Ok(ast) => ast,
-        Err(err) => return Err(err),
-    };
+/// The parameters an input system can take, see parse_param.
+enum InputParam {
+    /// `&T`, `Option<&T>` or `Entity`, read from the entity being scored.
+    Query { name: Ident, param: QueryParam },
+    /// `With<T>` or `Without<T>`, restricts the entities that are scored.
+    Filter { ty: Type },
+    /// `Res<T>`, a resource.
+    Resource { name: Ident, ty: Type },
+}
+
+pub(crate) fn input_system(
+    args: TokenStream,
+    input: TokenStream,
+) -> Result<TokenStream, Error> {
+    let item_fn = syn::parse::<ItemFn>(input)?;
 
     let name = item_fn.sig.ident;
-    let quoted_name = format!("{}", name);
-
-    let mut arg_names = Vec::new();
-    let mut arg_types = Vec::new();
-
-    for input in item_fn.sig.inputs {
-        match input {
-            FnArg::Receiver(_) => panic!("Input function cannot have self"),
-            FnArg::Typed(arg) => {
-                arg_names.push(match arg.pat.as_ref() {
-                    syn::Pat::Ident(ident) => ident.ident.clone(),
-                    _ => {
-                        return Err(Error::new_spanned(
-                            arg.pat.into_token_stream(),
-                            "Expected an Identity".to_string(),
-                        ));
-                    }
-                });
-                arg_types.push(match arg.ty.as_ref() {
-                    syn::Type::Reference(reference) => match &reference.elem.as_ref() {
-                        syn::Type::Path(path) => path
-                            .path
-                            .segments
-                            .last()
-                            .unwrap_or_else(|| panic!("What? {:?}", path.path))
-                            .clone(),
-                        _ => {
-                            return Err(Error::new_spanned(
-                                reference.elem.clone().into_token_stream(),
-                                "Expected a Component".to_string(),
-                            ));
-                        }
-                    },
-                    _ => {
-                        return Err(Error::new_spanned(
-                            arg.ty.into_token_stream(),
-                            "Expected the parameter type to be a reference".to_string(),
-                        ));
-                    }
-                });
+    let vis = item_fn.vis;
+    let input_name = InputName::parse(args, &name)?;
+    let input_name_definition = input_name.definition(&vis);
+    let input_name_param = input_name.param();
+    let name_expr = input_name.name();
+
+    match &item_fn.sig.output {
+        ReturnType::Type(_, ty) if ty.to_token_stream().to_string() == "f32" => {}
+        output => {
+            return Err(Error::new_spanned(
+                if matches!(output, ReturnType::Default) {
+                    name.to_token_stream()
+                } else {
+                    output.to_token_stream()
+                },
+                "Input systems must return an f32 score".to_string(),
+            ));
+        }
+    }
+
+    let params = item_fn
+        .sig
+        .inputs
+        .iter()
+        .map(parse_param)
+        .collect::<Result<Vec<InputParam>, Error>>()?;
+
+    let mut query_names = Vec::new();
+    let mut query_items = Vec::new();
+    let mut filters = Vec::new();
+    let mut resource_names = Vec::new();
+    let mut resource_types = Vec::new();
+    let mut change_ticks = Vec::new();
+    let mut bindings = Vec::new();
+
+    for param in params {
+        match param {
+            InputParam::Query { name, param } => {
+                let value = param.value(&name, &format_ident!("entity"));
+                bindings.push(quote! { let #name = #value; });
+                if let Some(query_item) = param.query_item() {
+                    query_items.push(query_item);
+                    change_ticks.extend(param.change_ticks(&name));
+                    query_names.push(name);
+                }
+            }
+            InputParam::Filter { ty } => filters.push(ty),
+            InputParam::Resource { name, ty } => {
+                change_ticks
+                    .push(quote! { bevy::prelude::DetectChanges::last_changed(&#name) });
+                resource_names.push(name);
+                resource_types.push(ty);
             }
-        };
+        }
     }
 
     let body = item_fn.block;
 
+    let skip_subject = skip_subject("input");
+    let score = call_body(&body, &quote! { f32 });
+
     let output = quote! {
-        fn #name(
-            mut query: bevy::prelude::Query<(bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta #(, &#arg_types)*)>,
+        #input_name_definition
+
+        #vis fn #name(
+            #input_name_param
+            mut query: bevy::prelude::Query<
+                (bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta #(, #query_items)*),
+                (#(#filters, )*)
+            >,
             res_ai_definitions: bevy::prelude::Res<bevy_utility_ai::AIDefinitions>
+            #(, #resource_names: #resource_types)*
         ) {
-            let _span = bevy::prelude::debug_span!("Calculating Input", input = #quoted_name).entered();
+            let _span = bevy::prelude::debug_span!("Calculating Input", input = #name_expr).entered();
+
+            let key = bevy_utility_ai::InputId::from_name(#name_expr);
 
-            let key = #name as usize;
-            for (entity, mut ai_meta #(, #arg_names)*) in query.iter_mut() {
+            // entities are scored independently, so they are spread across the ComputeTaskPool
+            query.par_iter_mut().for_each_mut(|(entity, mut ai_meta #(, #query_names)*)| {
                 let _span = bevy::prelude::debug_span!("", entity = entity.index()).entered();
 
-                let is_required = res_ai_definitions
-                    .map[&ai_meta.ai_definition]
-                    .required_inputs
-                    .contains(&key);
-                if !is_required {
-                    bevy::prelude::debug!("skipped as does not require this input");
-                    continue;
+                #skip_subject
+
+                // cached scores are reused until the components they were calculated from change
+                let max_cache_age = res_ai_definitions.map[&ai_meta.ai_definition]
+                    .cached_inputs
+                    .get(&key)
+                    .copied();
+                let change_ticks = bevy_utility_ai::ai_meta::CachedInput::fingerprint([
+                    #(#change_ticks),*
+                ]);
+                if let Some(max_cache_age) = max_cache_age {
+                    let is_reused = ai_meta.input_scores.contains_key(&key)
+                        && ai_meta
+                            .input_cache
+                            .get_mut(&key)
+                            .is_some_and(|cached| cached.reuse(change_ticks, max_cache_age));
+                    if is_reused {
+                        bevy::prelude::debug!("reusing cached score");
+                        return;
+                    }
                 }
 
-                let score = #body;
+                #(#bindings)*
+                let score = #score;
                 let mut entry = ai_meta.input_scores.entry(key).or_insert(f32::NEG_INFINITY);
                 *entry = score;
+                if max_cache_age.is_some() {
+                    ai_meta.input_cache.insert(key, bevy_utility_ai::ai_meta::CachedInput::new(change_ticks));
+                }
                 bevy::prelude::debug!("score {:.2}", score);
-            }
+            });
         }
     };
 
     Ok(output.into())
 }
+
+fn parse_param(input: &FnArg) -> Result<InputParam, Error> {
+    let arg = match input {
+        FnArg::Receiver(receiver) => {
+            return Err(Error::new_spanned(
+                receiver.into_token_stream(),
+                "Input systems cannot take self".to_string(),
+            ));
+        }
+        FnArg::Typed(arg) => arg,
+    };
+
+    let name = match arg.pat.as_ref() {
+        syn::Pat::Ident(ident) => Some(ident.ident.clone()),
+        syn::Pat::Wild(_) => None,
+        _ => {
+            return Err(Error::new_spanned(
+                arg.pat.clone().into_token_stream(),
+                "Expected an Identity".to_string(),
+            ));
+        }
+    };
+    let require_name = || {
+        name.clone().ok_or_else(|| {
+            Error::new_spanned(
+                arg.pat.clone().into_token_stream(),
+                "Expected an Identity, only filters can be named '_'".to_string(),
+            )
+        })
+    };
+
+    if let Some(param) = QueryParam::parse(&arg.ty)? {
+        return Ok(InputParam::Query {
+            name: require_name()?,
+            param,
+        });
+    }
+    if let Some(ty) = parse_resource(&arg.ty)? {
+        return Ok(InputParam::Resource {
+            name: require_name()?,
+            ty,
+        });
+    }
+    if is_filter(&arg.ty) {
+        return Ok(InputParam::Filter {
+            ty: arg.ty.as_ref().clone(),
+        });
+    }
+    Err(unsupported_param(arg.ty.to_token_stream()))
+}
+
+/// Returns whether the type is a `With<T>` or `Without<T>` query filter.
+fn is_filter(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    let segment = path.path.segments.last().unwrap();
+    matches!(segment.ident.to_string().as_str(), "With" | "Without")
+        && generic_arg(segment).is_some()
+}
+
+fn unsupported_param(ty: TokenStream2) -> Error {
+    Error::new_spanned(
+        ty,
+        "Unsupported parameter type, expected &T, Option<&T>, Entity, With<T>, Without<T> \
+        or Res<T>"
+            .to_string(),
+    )
+}
@@ -1,4 +1,7 @@
 mod input_system;
+mod multi_targeted_input_system;
+mod params;
+mod target_selector;
 mod targeted_input_system;
 
 use proc_macro::TokenStream;
@@ -19,10 +22,18 @@ pub fn targeted_input_system(args: TokenStream, input: TokenStream) -> TokenStre
     }
 }
 
-// #[proc_macro_attribute]
-// pub fn target_selector(args: TokenStream, input: TokenStream) -> TokenStream {
-//     match target_selector::target_selector(args, input) {
-//         Ok(tokens) => tokens,
-//         Err(err) => err.into_compile_error().into(),
-//     }
-// }
+#[proc_macro_attribute]
+pub fn multi_targeted_input_system(args: TokenStream, input: TokenStream) -> TokenStream {
+    match multi_targeted_input_system::multi_targeted_input_system(args, input) {
+        Ok(tokens) => tokens,
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+#[proc_macro_attribute]
+pub fn target_selector(args: TokenStream, input: TokenStream) -> TokenStream {
+    match target_selector::target_selector(args, input) {
+        Ok(tokens) => tokens,
+        Err(err) => err.into_compile_error().into(),
+    }
+}
@@ -0,0 +1,244 @@
+use crate::params::{
+    call_score_body, parse_input, parse_resource, returns_option_score, skip_subject,
+    InputName, ParsedInput,
+};
+use proc_macro::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::__private::TokenStream2;
+use syn::{Error, FnArg, ItemFn, Type};
+
+pub(crate) fn multi_targeted_input_system(
+    args: TokenStream,
+    input: TokenStream,
+) -> Result<TokenStream, Error> {
+    let item_fn = syn::parse::<ItemFn>(input)?;
+
+    let name = item_fn.sig.ident;
+    let vis = item_fn.vis;
+    let input_name = InputName::parse(args, &name)?;
+    let input_name_definition = input_name.definition(&vis);
+    let input_name_param = input_name.param();
+    let name_expr = input_name.name();
+
+    let returns_option = returns_option_score(
+        &item_fn.sig.output,
+        &name,
+        "Multi targeted input systems must return an f32 score, or an Option<f32> where None \
+        marks the combination of targets as invalid",
+    )?;
+
+    let mut subject_input: Option<ParsedInput> = None;
+    let mut target_inputs: Option<Vec<ParsedInput>> = None;
+    let mut resource_names = Vec::new();
+    let mut resource_types = Vec::new();
+
+    for input in &item_fn.sig.inputs {
+        let arg = match input {
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver.into_token_stream(),
+                    "Input systems cannot take self".to_string(),
+                ));
+            }
+            FnArg::Typed(arg) => arg,
+        };
+        let ident = match arg.pat.as_ref() {
+            syn::Pat::Ident(ident) => ident.ident.clone(),
+            _ => {
+                return Err(Error::new_spanned(
+                    arg.pat.clone().into_token_stream(),
+                    "Expected an Identity".to_string(),
+                ));
+            }
+        };
+
+        if let Some(ty) = parse_resource(&arg.ty)? {
+            resource_names.push(ident);
+            resource_types.push(ty);
+            continue;
+        }
+
+        match ident.to_string().as_str() {
+            "subject" if subject_input.is_none() => {
+                subject_input = Some(parse_input(ident, &arg.ty)?);
+            }
+            "targets" if target_inputs.is_none() => {
+                // each element of the tuple is a target, parsed like the target of a
+                // targeted input system
+                let slots = match arg.ty.as_ref() {
+                    Type::Tuple(tuple) if tuple.elems.len() >= 2 => tuple
+                        .elems
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, ty)| parse_input(format_ident!("target_{}", idx), ty))
+                        .collect::<Result<Vec<ParsedInput>, Error>>()?,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            arg.ty.clone().into_token_stream(),
+                            "Expected a tuple of at least two targets".to_string(),
+                        ));
+                    }
+                };
+                target_inputs = Some(slots);
+            }
+            "subject" | "targets" => {
+                return Err(Error::new_spanned(
+                    input.clone().into_token_stream(),
+                    format!("There already exists an input named '{}'", ident),
+                ));
+            }
+            _ => {
+                return Err(Error::new_spanned(
+                    input.clone().into_token_stream(),
+                    "Function can only have two inputs parameters, one named \
+                    'subject' (optional), and one named 'targets' (required), along with any \
+                    Res<T> parameters"
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    let Some(target_inputs) = target_inputs else {
+        return Err(Error::new_spanned(
+            item_fn.sig.inputs.into_token_stream(),
+            "Function must have an input named 'targets'".to_string(),
+        ));
+    };
+
+    let (subject_arg_names, subject_query_items, subject_data_line) = match &subject_input {
+        Some(subject_input) => (
+            subject_input.arg_names(),
+            subject_input.query_items(),
+            subject_input.binding(&format_ident!("subject_entity_id")),
+        ),
+        None => (Vec::new(), Vec::new(), TokenStream2::new()),
+    };
+
+    let target_entities: Vec<_> = (0..target_inputs.len())
+        .map(|idx| format_ident!("target_entity_{}", idx))
+        .collect();
+    let target_queries: Vec<_> = (0..target_inputs.len())
+        .map(|idx| format_ident!("q_target_{}", idx))
+        .collect();
+    let target_query_items: Vec<_> = target_inputs
+        .iter()
+        .map(|target_input| {
+            let query_items = target_input.query_items();
+            quote! { bevy::prelude::Query<(bevy::prelude::Entity #(, #query_items)*)> }
+        })
+        .collect();
+    let target_idents: Vec<_> = target_inputs.iter().map(|input| &input.ident).collect();
+
+    let body = item_fn.block;
+
+    let score = call_score_body(&body, returns_option);
+
+    // scores a single combination of targets, invalid combinations are not scored
+    let mut score_combination = quote! {
+        let targets = (#(#target_idents, )*);
+        let score: Option<f32> = #score;
+        match score {
+            Some(score) => {
+                bevy::prelude::debug!("score {:.2}", score);
+                score_map.insert(vec![#(#target_entities),*], score);
+            }
+            None => bevy::prelude::debug!("invalid targets"),
+        }
+    };
+
+    // nest a loop over each target's candidates, binding the target before the inner loops so
+    // that its components are only unwrapped once
+    let target_candidates: Vec<_> = (0..target_inputs.len())
+        .map(|idx| format_ident!("target_candidates_{}", idx))
+        .collect();
+    for (idx, target_input) in target_inputs.iter().enumerate().rev() {
+        let entity = &target_entities[idx];
+        let previous_entities = &target_entities[..idx];
+        let query = &target_queries[idx];
+        let candidates = &target_candidates[idx];
+        let arg_names = target_input.arg_names();
+        let binding = target_input.binding(entity);
+        score_combination = quote! {
+            for &#entity in &#candidates {
+                // a combination can't contain the subject or the same target twice
+                if #entity == subject_entity_id #(|| #entity == #previous_entities)* {
+                    continue;
+                }
+                let Ok((#entity #(, #arg_names)*)) = #query.get(#entity) else {
+                    continue;
+                };
+                #binding
+                #score_combination
+            }
+        };
+    }
+
+    // the candidates for each target are the entities that pass its filter, see
+    // Consideration::for_target
+    let collect_candidates = target_queries
+        .iter()
+        .zip(&target_candidates)
+        .enumerate()
+        .map(|(idx, (query, candidates))| {
+            quote! {
+                let target_filter = target_filters.and_then(|target_filters| target_filters.get(#idx));
+                let #candidates: Vec<bevy::prelude::Entity> = match target_filter
+                    .and_then(|target_filter| res_ai_target_entity_sets.candidates(target_filter, subject_entity_id, None))
+                {
+                    Some(candidates) => candidates,
+                    None => #query
+                        .iter()
+                        .map(|item| item.0)
+                        .filter(|&entity| {
+                            target_filter.is_none_or(|target_filter| {
+                                !res_ai_target_entity_sets.is_excluded(target_filter, entity)
+                            })
+                        })
+                        .collect(),
+                };
+            }
+        });
+
+    let skip_subject = skip_subject("input");
+
+    let output = quote! {
+        #input_name_definition
+
+        #vis fn #name(
+            #input_name_param
+            mut q_subject: bevy::prelude::Query<(bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta #(, #subject_query_items)*)>,
+            #(#target_queries: #target_query_items,)*
+            res_ai_definitions: bevy::prelude::Res<bevy_utility_ai::AIDefinitions>,
+            res_ai_target_entity_sets: bevy::prelude::Res<bevy_utility_ai::AITargetEntitySets>
+            #(, #resource_names: #resource_types)*
+        ) {
+            let _span = bevy::prelude::debug_span!("Calculating Multi Targeted Input", input = #name_expr).entered();
+            let key = bevy_utility_ai::InputId::from_name(#name_expr);
+
+            // subjects are scored independently, so they are spread across the ComputeTaskPool
+            q_subject.par_iter_mut().for_each_mut(|(subject_entity_id, mut ai_meta #(, #subject_arg_names)*)| {
+                let _span = bevy::prelude::debug_span!("", entity = subject_entity_id.index()).entered();
+
+                #skip_subject
+
+                let target_filters = res_ai_definitions
+                    .map[&ai_meta.ai_definition]
+                    .multi_targeted_input_filter_sets
+                    .get(&key);
+                #(#collect_candidates)*
+
+                #subject_data_line
+
+                // every combination is rescored, so combinations that are no longer valid are
+                // dropped along with the previous scores
+                let mut score_map = bevy::utils::HashMap::new();
+                #score_combination
+                bevy::prelude::debug!("scored {} combinations of targets", score_map.len());
+                ai_meta.multi_targeted_input_scores.insert(key, score_map);
+            });
+        }
+    };
+
+    Ok(output.into())
+}
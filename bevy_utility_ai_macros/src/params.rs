@@ -0,0 +1,325 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::__private::TokenStream2;
+use syn::parse::Parser;
+use syn::{
+    Block, Error, GenericArgument, Ident, LitStr, PathArguments, PathSegment, ReturnType,
+    Type, Visibility,
+};
+
+/// The name of an input system, which its InputId is derived from. This is the module path and
+/// name of the function unless a name is given with `#[input_system(name = "...")]`.
+pub(crate) struct InputName {
+    label: Ident,
+    name: TokenStream2,
+}
+
+impl InputName {
+    /// Parses the macro's args, the only one supported is `name = "..."`.
+    pub(crate) fn parse(args: TokenStream, fn_name: &Ident) -> Result<Self, Error> {
+        let mut name: Option<LitStr> = None;
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("name") && name.is_none() {
+                name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("Unsupported argument, expected name = \"...\""))
+            }
+        });
+        parser.parse(args)?;
+
+        let name_expr = format!("{}", fn_name);
+        Ok(Self {
+            label: format_ident!("__{}_input_name", fn_name),
+            name: match name {
+                Some(name) => name.into_token_stream(),
+                None => quote! { concat!(module_path!(), "::", #name_expr) },
+            },
+        })
+    }
+
+    /// The type implementing InputName for the system, this must be in the same module as the
+    /// system so that module_path! gives the system's module.
+    pub(crate) fn definition(&self, vis: &Visibility) -> TokenStream2 {
+        let label = &self.label;
+        let name = &self.name;
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #vis struct #label;
+
+            impl bevy_utility_ai::input_id::InputName for #label {
+                const NAME: &'static str = #name;
+            }
+        }
+    }
+
+    /// The first param of the system, which lets InputId::of read the name from its type.
+    pub(crate) fn param(&self) -> TokenStream2 {
+        let label = &self.label;
+        quote! {
+            _input_name: bevy::prelude::Local<bevy_utility_ai::input_id::InputLabel<#label>>,
+        }
+    }
+
+    /// The name as a `&'static str` expression.
+    pub(crate) fn name(&self) -> TokenStream2 {
+        let label = &self.label;
+        quote! { <#label as bevy_utility_ai::input_id::InputName>::NAME }
+    }
+}
+
+/// The data an input system can read from the entity being scored.
+pub(crate) enum QueryParam {
+    /// `&T`, a component on the entity.
+    Component(Type),
+    /// `Option<&T>`, a component the entity may not have.
+    OptionalComponent(Type),
+    /// `Entity`, the entity itself.
+    Entity,
+}
+
+impl QueryParam {
+    /// Parses `&T`, `Option<&T>` or `Entity`, returning None for any other type.
+    pub(crate) fn parse(ty: &Type) -> Result<Option<Self>, Error> {
+        match ty {
+            Type::Reference(reference) => {
+                if reference.mutability.is_some() {
+                    return Err(Error::new_spanned(
+                        reference.into_token_stream(),
+                        "Input systems cannot mutate components, use &T instead".to_string(),
+                    ));
+                }
+                Ok(Some(QueryParam::Component(reference.elem.as_ref().clone())))
+            }
+            Type::Path(path) => {
+                let segment = path.path.segments.last().unwrap();
+                match (segment.ident.to_string().as_str(), generic_arg(segment)) {
+                    ("Option", Some(Type::Reference(reference)))
+                        if reference.mutability.is_none() =>
+                    {
+                        Ok(Some(QueryParam::OptionalComponent(
+                            reference.elem.as_ref().clone(),
+                        )))
+                    }
+                    ("Entity", None) => Ok(Some(QueryParam::Entity)),
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The item added to the query, entities are always the first item of the query so they
+    /// don't add one.
+    pub(crate) fn query_item(&self) -> Option<TokenStream2> {
+        match self {
+            QueryParam::Component(ty) => Some(quote! { bevy::prelude::Ref<#ty> }),
+            QueryParam::OptionalComponent(ty) => {
+                Some(quote! { Option<bevy::prelude::Ref<#ty>> })
+            }
+            QueryParam::Entity => None,
+        }
+    }
+
+    /// The tick the query item named `name` last changed on, used to fingerprint cached
+    /// inputs.
+    pub(crate) fn change_ticks(&self, name: &Ident) -> Option<TokenStream2> {
+        match self {
+            QueryParam::Component(_) => {
+                Some(quote! { bevy::prelude::DetectChanges::last_changed(&#name) })
+            }
+            QueryParam::OptionalComponent(_) => Some(quote! {
+                #name.as_ref().map_or(0, |r| bevy::prelude::DetectChanges::last_changed(r))
+            }),
+            QueryParam::Entity => None,
+        }
+    }
+
+    /// The value passed to the function body for the query item named `name`.
+    pub(crate) fn value(&self, name: &Ident, entity: &Ident) -> TokenStream2 {
+        match self {
+            QueryParam::Component(_) => quote! { #name.into_inner() },
+            QueryParam::OptionalComponent(_) => quote! { #name.map(|r| r.into_inner()) },
+            QueryParam::Entity => quote! { #entity },
+        }
+    }
+}
+
+/// The `subject` or `target` input, either a single parameter or a tuple of them.
+pub(crate) struct ParsedInput {
+    pub(crate) ident: Ident,
+    params: Vec<QueryParam>,
+    is_tuple: bool,
+}
+
+impl ParsedInput {
+    /// The names the query items are bound to, entities don't have a query item.
+    pub(crate) fn arg_names(&self) -> Vec<Ident> {
+        self.query_params()
+            .map(|(idx, _)| format_ident!("{}_{}", self.ident, idx))
+            .collect()
+    }
+
+    pub(crate) fn query_items(&self) -> Vec<TokenStream2> {
+        self.params
+            .iter()
+            .filter_map(QueryParam::query_item)
+            .collect()
+    }
+
+    pub(crate) fn change_ticks(&self) -> Vec<TokenStream2> {
+        self.query_params()
+            .filter_map(|(idx, param)| {
+                param.change_ticks(&format_ident!("{}_{}", self.ident, idx))
+            })
+            .collect()
+    }
+
+    /// Binds the input to the value the function body expects.
+    pub(crate) fn binding(&self, entity: &Ident) -> TokenStream2 {
+        let ident = &self.ident;
+        let mut idx = 0usize;
+        let values: Vec<TokenStream2> = self
+            .params
+            .iter()
+            .map(|param| {
+                let name = format_ident!("{}_{}", ident, idx);
+                if param.query_item().is_some() {
+                    idx += 1;
+                }
+                param.value(&name, entity)
+            })
+            .collect();
+        if self.is_tuple {
+            quote! { let #ident = (#(#values, )*); }
+        } else {
+            quote! { let #ident = #(#values)*; }
+        }
+    }
+
+    fn query_params(&self) -> impl Iterator<Item = (usize, &QueryParam)> {
+        self.params
+            .iter()
+            .filter(|param| param.query_item().is_some())
+            .enumerate()
+    }
+}
+
+pub(crate) fn parse_input(ident: Ident, ty: &Type) -> Result<ParsedInput, Error> {
+    let parse_param = |ty: &Type| {
+        QueryParam::parse(ty)?.ok_or_else(|| {
+            Error::new_spanned(
+                ty.into_token_stream(),
+                "Unsupported parameter type, expected &T, Option<&T> or Entity".to_string(),
+            )
+        })
+    };
+
+    let (params, is_tuple) = match ty {
+        Type::Tuple(tuple) => (
+            tuple
+                .elems
+                .iter()
+                .map(parse_param)
+                .collect::<Result<Vec<QueryParam>, Error>>()?,
+            true,
+        ),
+        _ => (vec![parse_param(ty)?], false),
+    };
+
+    Ok(ParsedInput {
+        ident,
+        params,
+        is_tuple,
+    })
+}
+
+/// Parses `Res<T>`, returning None for any other type.
+pub(crate) fn parse_resource(ty: &Type) -> Result<Option<Type>, Error> {
+    let Type::Path(path) = ty else {
+        return Ok(None);
+    };
+    let segment = path.path.segments.last().unwrap();
+    match (segment.ident.to_string().as_str(), generic_arg(segment)) {
+        ("Res", Some(_)) => Ok(Some(ty.clone())),
+        ("ResMut", _) => Err(Error::new_spanned(
+            ty.into_token_stream(),
+            "Input systems cannot mutate resources, use Res<T> instead".to_string(),
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// Returns early from the closure run for each subject if its AI doesn't require the system's
+/// `key`, or if it isn't due to be evaluated on this tick. Expects `res_ai_definitions`,
+/// `ai_meta` and `key` to be in scope, `kind` is what the system is, e.g. "input".
+pub(crate) fn skip_subject(kind: &str) -> TokenStream2 {
+    let not_required_message = format!("skipped as does not require this {}", kind);
+    quote! {
+        // the AI may not be defined yet if it is loaded from an asset
+        let is_required = res_ai_definitions
+            .map
+            .get(&ai_meta.ai_definition)
+            .is_some_and(|ai_definition| ai_definition.required_inputs.contains(&key));
+        if !is_required {
+            bevy::prelude::debug!(#not_required_message);
+            return;
+        }
+
+        // entities are only evaluated on the ticks they are due, see set_decision_interval
+        if !ai_meta.is_due {
+            bevy::prelude::debug!("skipped as not due for evaluation");
+            return;
+        }
+    }
+}
+
+/// Calls the function body, which is wrapped in a closure so that it can return early.
+pub(crate) fn call_body(body: &Block, output: &TokenStream2) -> TokenStream2 {
+    quote! { (|| -> #output #body)() }
+}
+
+/// Calls the body of a targeted input system, whose score is an `Option<f32>` where None
+/// marks the target as invalid, see returns_option_score.
+pub(crate) fn call_score_body(body: &Block, returns_option: bool) -> TokenStream2 {
+    if returns_option {
+        call_body(body, &quote! { Option<f32> })
+    } else {
+        let score = call_body(body, &quote! { f32 });
+        quote! { Some(#score) }
+    }
+}
+
+/// Returns whether the function returns an `Option<f32>` score rather than an `f32`, any other
+/// return type is an error with the message.
+pub(crate) fn returns_option_score(
+    output: &ReturnType,
+    name: &Ident,
+    message: &str,
+) -> Result<bool, Error> {
+    if let ReturnType::Type(_, ty) = output {
+        match ty.to_token_stream().to_string().as_str() {
+            "f32" => return Ok(false),
+            "Option < f32 >" => return Ok(true),
+            _ => {}
+        }
+    }
+    Err(Error::new_spanned(
+        match output {
+            ReturnType::Default => name.to_token_stream(),
+            _ => output.to_token_stream(),
+        },
+        message.to_string(),
+    ))
+}
+
+pub(crate) fn generic_arg(segment: &PathSegment) -> Option<&Type> {
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
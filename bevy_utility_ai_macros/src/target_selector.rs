@@ -1,68 +1,116 @@
+use crate::params::{call_body, parse_input, skip_subject, InputName, ParsedInput};
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
-use syn::{Error, FnArg, ItemFn, Pat, Type};
+use quote::{format_ident, quote, ToTokens};
+use syn::__private::TokenStream2;
+use syn::{Error, FnArg, ItemFn, Pat, ReturnType, Type};
 
 pub(crate) fn target_selector(
-    _: TokenStream,
+    args: TokenStream,
     input: TokenStream,
 ) -> Result<TokenStream, Error> {
-    let item_fn = match syn::parse::<ItemFn>(input) {
-        Ok(ast) => ast,
-        Err(err) => return Err(err),
-    };
+    let item_fn = syn::parse::<ItemFn>(input)?;
 
     let name = item_fn.sig.ident;
-    let body = item_fn.block;
+    let vis = item_fn.vis;
+    let input_name = InputName::parse(args, &name)?;
+    let input_name_definition = input_name.definition(&vis);
+    let input_name_param = input_name.param();
+    let name_expr = input_name.name();
 
-    if item_fn.sig.inputs.len() != 1 {
+    let returns_vec = match &item_fn.sig.output {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Vec"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    };
+    if !returns_vec {
         return Err(Error::new_spanned(
-            item_fn.sig.inputs.into_token_stream(),
-            "Expected only one argument".to_string(),
+            if matches!(item_fn.sig.output, ReturnType::Default) {
+                name.to_token_stream()
+            } else {
+                item_fn.sig.output.to_token_stream()
+            },
+            "Target selectors must return a Vec<Entity> of the selected targets".to_string(),
         ));
     }
 
-    let input = match &item_fn.sig.inputs[0] {
-        FnArg::Receiver(_) => panic!(),
-        FnArg::Typed(typed_input) => typed_input,
-    };
+    // the optional subject is read from the entity making the decision, any other parameters
+    // are passed through as system params
+    let mut subject_input: Option<ParsedInput> = None;
+    let mut system_params = Vec::new();
 
-    let input_ident = match input.pat.as_ref() {
-        Pat::Ident(ident) => ident,
-        _ => {
-            return Err(Error::new_spanned(
-                input,
-                "expected an identity here!".to_string(),
-            ))
+    for input in &item_fn.sig.inputs {
+        let arg = match input {
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver.into_token_stream(),
+                    "Target selectors cannot take self".to_string(),
+                ));
+            }
+            FnArg::Typed(arg) => arg,
+        };
+        match arg.pat.as_ref() {
+            Pat::Ident(ident) if ident.ident == "subject" => {
+                if subject_input.is_some() {
+                    return Err(Error::new_spanned(
+                        input.clone().into_token_stream(),
+                        "There already exists an input named 'subject'".to_string(),
+                    ));
+                }
+                subject_input = Some(parse_input(ident.ident.clone(), &arg.ty)?);
+            }
+            Pat::Ident(_) => system_params.push(arg.clone()),
+            _ => {
+                return Err(Error::new_spanned(
+                    arg.pat.clone().into_token_stream(),
+                    "Expected an Identity".to_string(),
+                ));
+            }
         }
-    };
+    }
 
-    let input_ty = match input.ty.as_ref() {
-        Type::Path(path) => path.to_token_stream(),
-        _ => {
-            return Err(Error::new_spanned(
-                input,
-                "expected an type path here!".to_string(),
-            ));
-        }
+    let (subject_arg_names, subject_query_items, subject_data_line) = match &subject_input {
+        Some(subject_input) => (
+            subject_input.arg_names(),
+            subject_input.query_items(),
+            subject_input.binding(&format_ident!("subject_entity_id")),
+        ),
+        None => (Vec::new(), Vec::new(), TokenStream2::new()),
     };
 
+    let body = item_fn.block;
+
+    let skip_subject = skip_subject("target selector");
+    let targets = call_body(&body, &quote! { Vec<bevy::prelude::Entity> });
+
     let output = quote! {
-        fn #name(
-            mut q_subject: bevy::prelude::Query<(bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta)>,
-            #input_ident: #input_ty,
-            ai_definitions: bevy::prelude::Res<bevy_utility_ai::AIDefinitions>,
+        #input_name_definition
+
+        #vis fn #name(
+            #input_name_param
+            mut q_subject: bevy::prelude::Query<(bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta #(, #subject_query_items)*)>,
+            res_ai_definitions: bevy::prelude::Res<bevy_utility_ai::AIDefinitions>
+            #(, #system_params)*
         ) {
-            let target_selector_key = #name as usize;
-            for (entity, mut ai_meta) in q_subject.iter_mut() {
-                let targets = #body;
+            let _span = bevy::prelude::debug_span!("Selecting Targets", selector = #name_expr).entered();
+            let key = bevy_utility_ai::InputId::from_name(#name_expr);
 
+            // subjects select their targets independently, so they are spread across the ComputeTaskPool
+            q_subject.par_iter_mut().for_each_mut(|(subject_entity_id, mut ai_meta #(, #subject_arg_names)*)| {
+                let _span = bevy::prelude::debug_span!("", entity = subject_entity_id.index()).entered();
 
-                if let Some(val) = ai_meta.targeted_input_targets.get_mut(&key) {
-                    *val = targets;
-                } else {
-                    ai_meta.targeted_input_targets.insert(key, targets);
-                }
-            }
+                #skip_subject
+
+                #subject_data_line
+                let targets = #targets;
+                bevy::prelude::debug!("selected {} targets", targets.len());
+                ai_meta.selected_targets.insert(key, targets);
+            });
         }
     };
 
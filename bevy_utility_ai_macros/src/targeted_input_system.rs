@@ -1,256 +1,257 @@
+use crate::params::{
+    call_score_body, parse_input, parse_resource, returns_option_score, skip_subject,
+    InputName, ParsedInput,
+};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote, ToTokens};
 use syn::__private::TokenStream2;
-use syn::{Error, FnArg, Ident, ItemFn, PathSegment, Type};
+use syn::{Error, FnArg, ItemFn};
 
 pub(crate) fn targeted_input_system(
-    _: TokenStream,
+    args: TokenStream,
     input: TokenStream,
 ) -> Result<TokenStream, Error> {
-    let item_fn = match syn::parse::<ItemFn>(input) {
-        Ok(ast) => ast,
-        Err(err) => return Err(err),
-    };
+    let item_fn = syn::parse::<ItemFn>(input)?;
 
     let name = item_fn.sig.ident;
-    let quoted_name = format!("{}", name);
+    let vis = item_fn.vis;
+    let input_name = InputName::parse(args, &name)?;
+    let input_name_definition = input_name.definition(&vis);
+    let input_name_param = input_name.param();
+    let name_expr = input_name.name();
 
-    if item_fn.sig.inputs.len() > 2 {
-        return Err(Error::new_spanned(
-            item_fn.sig.inputs.into_token_stream(),
-            "Expected two inputs".to_string(),
-        ));
-    }
-
-    let parsed_inputs_result: Result<Vec<ParsedInput>, Error> = item_fn
-        .sig
-        .inputs
-        .iter()
-        .map(parse_input)
-        .collect::<Vec<Result<ParsedInput, Error>>>()
-        .into_iter()
-        .collect();
+    let returns_option = returns_option_score(
+        &item_fn.sig.output,
+        &name,
+        "Targeted input systems must return an f32 score, or an Option<f32> where None marks \
+        the target as invalid",
+    )?;
 
-    let parsed_inputs = parsed_inputs_result?;
-    let mut subject_input: Option<&ParsedInput> = None;
-    let mut target_input: Option<&ParsedInput> = None;
+    let mut subject_input: Option<ParsedInput> = None;
+    let mut target_input: Option<ParsedInput> = None;
+    let mut resource_names = Vec::new();
+    let mut resource_types = Vec::new();
 
-    for (idx, input) in parsed_inputs.iter().enumerate() {
-        match input.ident.to_string().as_str() {
-            "subject" => {
-                if subject_input.is_some() {
-                    return Err(Error::new_spanned(
-                        item_fn.sig.inputs[idx].clone().into_token_stream(),
-                        "There already exists an input named 'subject'".to_string(),
-                    ));
-                }
-                subject_input = Some(input);
+    for input in &item_fn.sig.inputs {
+        let arg = match input {
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver.into_token_stream(),
+                    "Input systems cannot take self".to_string(),
+                ));
             }
-            "target" => {
-                if target_input.is_some() {
-                    return Err(Error::new_spanned(
-                        item_fn.sig.inputs[idx].clone().into_token_stream(),
-                        "There already exists an input named 'target'".to_string(),
-                    ));
-                }
-                target_input = Some(input);
+            FnArg::Typed(arg) => arg,
+        };
+        let ident = match arg.pat.as_ref() {
+            syn::Pat::Ident(ident) => ident.ident.clone(),
+            _ => {
+                return Err(Error::new_spanned(
+                    arg.pat.clone().into_token_stream(),
+                    "Expected an Identity".to_string(),
+                ));
             }
+        };
+
+        if let Some(ty) = parse_resource(&arg.ty)? {
+            resource_names.push(ident);
+            resource_types.push(ty);
+            continue;
+        }
+
+        let existing = match ident.to_string().as_str() {
+            "subject" => &mut subject_input,
+            "target" => &mut target_input,
             _ => {
                 return Err(Error::new_spanned(
-                    item_fn.sig.inputs[idx].clone().into_token_stream(),
+                    input.clone().into_token_stream(),
                     "Function can only have two inputs parameters, one named \
-                    'subject' (optional), and one named 'target' (required)"
+                    'subject' (optional), and one named 'target' (required), along with any \
+                    Res<T> parameters"
                         .to_string(),
                 ))
             }
+        };
+        if existing.is_some() {
+            return Err(Error::new_spanned(
+                input.clone().into_token_stream(),
+                format!("There already exists an input named '{}'", ident),
+            ));
         }
+        *existing = Some(parse_input(ident, &arg.ty)?);
     }
 
-    if target_input.is_none() {
+    let Some(target_input) = target_input else {
         return Err(Error::new_spanned(
             item_fn.sig.inputs.into_token_stream(),
             "Function must have an input named 'target'".to_string(),
         ));
-    }
+    };
 
-    let ParsedInput {
-        ident: target_ident,
-        arg_names: target_arg_names,
-        arg_types: target_arg_types,
-    } = target_input.unwrap();
+    let subject_entity = format_ident!("subject_entity_id");
+    let target_entity = format_ident!("entity_id");
 
-    let mut subject_ident = None;
-    let mut subject_arg_names = Vec::new();
-    let mut subject_arg_types = Vec::new();
+    let (subject_arg_names, subject_query_items) = match &subject_input {
+        Some(subject_input) => (subject_input.arg_names(), subject_input.query_items()),
+        None => (Vec::new(), Vec::new()),
+    };
+    let target_arg_names = target_input.arg_names();
+    let target_query_items = target_input.query_items();
 
-    if let Some(subject_input) = subject_input {
-        subject_ident = Some(subject_input.ident.clone());
-        subject_arg_names = subject_input.arg_names.clone();
-        subject_arg_types = subject_input.arg_types.clone();
-    }
+    // resources are shared by every target, so their change ticks are part of the subject's
+    let mut subject_change_ticks = match &subject_input {
+        Some(subject_input) => subject_input.change_ticks(),
+        None => Vec::new(),
+    };
+    subject_change_ticks.extend(
+        resource_names
+            .iter()
+            .map(|name| quote! { bevy::prelude::DetectChanges::last_changed(&#name) }),
+    );
+    let subject_change_tick_count = subject_change_ticks.len();
+    let target_change_ticks = target_input.change_ticks();
 
-    let subject_data_line = match subject_ident {
+    let subject_data_line = match &subject_input {
         None => TokenStream2::new(),
-        Some(ident) => quote! { let #ident = (#(#subject_arg_names, )*); },
+        Some(subject_input) => subject_input.binding(&subject_entity),
     };
+    let target_data_line = target_input.binding(&target_entity);
 
     let body = item_fn.block;
 
+    let score = call_score_body(&body, returns_option);
+
+    // scores a single target, reusing the cached score if the input is cached and none of the
+    // subject's or target's components have changed
+    let score_target = quote! {
+        let change_ticks = bevy_utility_ai::ai_meta::CachedInput::fingerprint(
+            subject_change_ticks.into_iter().chain([
+                #(#target_change_ticks),*
+            ])
+        );
+        if let Some(max_cache_age) = max_cache_age {
+            let is_reused = score_map.contains_key(&entity_id)
+                && cache_map
+                    .get_mut(&entity_id)
+                    .is_some_and(|cached| cached.reuse(change_ticks, max_cache_age));
+            if is_reused {
+                bevy::prelude::debug!("reusing cached score");
+                continue;
+            }
+        }
+        #target_data_line
+        let score: Option<f32> = #score;
+        let Some(score) = score else {
+            // invalid targets are not scored, so they won't be considered by any decision
+            score_map.remove(&entity_id);
+            cache_map.remove(&entity_id);
+            bevy::prelude::debug!("invalid target");
+            continue;
+        };
+        let entry = score_map.entry(entity_id).or_insert(f32::NEG_INFINITY);
+        *entry = score;
+        if max_cache_age.is_some() {
+            cache_map.insert(entity_id, bevy_utility_ai::ai_meta::CachedInput::new(change_ticks));
+        }
+        bevy::prelude::debug!("score {:.2}", score);
+    };
+
+    let skip_subject = skip_subject("input");
+
     let output = quote! {
-        fn #name(
-            mut q_subject: bevy::prelude::Query<(bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta #(, &#subject_arg_types)*)>,
-            q_target: bevy::prelude::Query<(bevy::prelude::Entity #(, &#target_arg_types)*)>,
+        #input_name_definition
+
+        #vis fn #name(
+            #input_name_param
+            mut q_subject: bevy::prelude::Query<(bevy::prelude::Entity, &mut bevy_utility_ai::AIMeta #(, #subject_query_items)*)>,
+            q_target: bevy::prelude::Query<(bevy::prelude::Entity #(, #target_query_items)*)>,
             res_ai_definitions: bevy::prelude::Res<bevy_utility_ai::AIDefinitions>,
             res_ai_target_entity_sets: bevy::prelude::Res<bevy_utility_ai::AITargetEntitySets>
+            #(, #resource_names: #resource_types)*
         ) {
-            let _span = bevy::prelude::debug_span!("Calculating Targeted Input", input = #quoted_name).entered();
-            let key = #name as usize;
+            let _span = bevy::prelude::debug_span!("Calculating Targeted Input", input = #name_expr).entered();
+            let key = bevy_utility_ai::InputId::from_name(#name_expr);
 
-            for (subject_entity_id, mut ai_meta #(, #subject_arg_names)*) in q_subject.iter_mut() {
+            // subjects are scored independently, so they are spread across the ComputeTaskPool
+            q_subject.par_iter_mut().for_each_mut(|(subject_entity_id, mut ai_meta #(, #subject_arg_names)*)| {
                 let _span = bevy::prelude::debug_span!("", entity = subject_entity_id.index()).entered();
 
-                let is_required = res_ai_definitions
-                    .map[&ai_meta.ai_definition]
-                    .required_inputs
-                    .contains(&key);
-                if !is_required {
-                    bevy::prelude::debug!("skipped calculating inputs for this entity");
-                    continue;
-                };
+                #skip_subject
 
-                // Vec<usize> representing the filter sets this system should care about
-                let targeted_input_filter_sets = res_ai_definitions
+                // TargetFilter representing the filter sets this system should care about
+                let target_filter = res_ai_definitions
                     .map[&ai_meta.ai_definition]
                     .targeted_input_filter_sets.get(&key);
 
-                let target_entities = match targeted_input_filter_sets {
-                    // Some implies that this system should only evaluate for a limited set of entities
-                    Some(target_entity_sets) => {
-                        Some(target_entity_sets
-                            .iter()
-                            .map(|&set_key| res_ai_target_entity_sets.get(set_key))
-                            .flatten()
-                            .collect::<Vec<bevy::prelude::Entity>>()
-                        )
-                    },
-                    // None implies we should check all entities
-                    None => None
-                };
+                // Some implies that this system should only evaluate for a limited set of entities,
+                // None implies we should check all entities that aren't excluded
+                let target_entities = target_filter.and_then(|target_filter| {
+                    // decisions with a target selector only consider the targets it selected
+                    let selected_targets = target_filter
+                        .selector
+                        .and_then(|selector| ai_meta.selected_targets.get(&selector))
+                        .map(Vec::as_slice);
+                    res_ai_target_entity_sets.candidates(target_filter, subject_entity_id, selected_targets)
+                });
 
+                // cached scores are reused until the components they were calculated from change
+                let max_cache_age = res_ai_definitions.map[&ai_meta.ai_definition]
+                    .cached_inputs
+                    .get(&key)
+                    .copied();
+                let subject_change_ticks: [u32; #subject_change_tick_count] = [
+                    #(#subject_change_ticks),*
+                ];
 
+                let ai_meta = ai_meta.into_inner();
                 let score_map = ai_meta
                     .targeted_input_scores
                     .entry(key)
                     .or_insert(bevy::utils::HashMap::new());
+                let cache_map = ai_meta.targeted_input_cache.entry(key).or_default();
 
                 #subject_data_line
 
                 if let Some(target_entities) = target_entities {
                     bevy::prelude::debug!("calculating input for {} filter set entities", target_entities.len());
+
+                    // purge scores for entities that are no longer valid targets
+                    let target_entity_set: bevy::utils::HashSet<bevy::prelude::Entity> =
+                        target_entities.iter().copied().collect();
+                    score_map.retain(|entity, _| target_entity_set.contains(entity) && q_target.contains(*entity));
+                    cache_map.retain(|entity, _| score_map.contains_key(entity));
+
                     for &target_entity in &target_entities {
-                        let (entity_id #(, #target_arg_names)*) = q_target.get(target_entity).unwrap();
+                        let Ok((entity_id #(, #target_arg_names)*)) = q_target.get(target_entity) else {
+                            continue;
+                        };
                         let _span = bevy::prelude::debug_span!("", target_entity = entity_id.index()).entered();
                         if entity_id == subject_entity_id {
                             continue;
                         }
-                        let #target_ident = (#(#target_arg_names, )*);
-                        let score =  #body;
-                        let entry = score_map.entry(entity_id).or_insert(f32::NEG_INFINITY);
-                        *entry = score;
-                        bevy::prelude::debug!("score {:.2}", score);
+                        #score_target
                     }
                 } else {
+                    let is_excluded = |entity: bevy::prelude::Entity| {
+                        target_filter.is_some_and(|target_filter| {
+                            res_ai_target_entity_sets.is_excluded(target_filter, entity)
+                        })
+                    };
+
+                    // purge scores for entities that no longer match the target query
+                    score_map.retain(|entity, _| q_target.contains(*entity) && !is_excluded(*entity));
+                    cache_map.retain(|entity, _| score_map.contains_key(entity));
+
                     for (entity_id #(, #target_arg_names)*) in q_target.iter() {
                         let _span = bevy::prelude::debug_span!("", target_entity = entity_id.index()).entered();
-                        if entity_id == subject_entity_id {
+                        if entity_id == subject_entity_id || is_excluded(entity_id) {
                             continue;
                         }
-                        let #target_ident = (#(#target_arg_names, )*);
-                        let score =  #body;
-                        let entry = score_map.entry(entity_id).or_insert(f32::NEG_INFINITY);
-                        *entry = score;
-                        bevy::prelude::debug!("score {:.2}", score);
+                        #score_target
                     }
                 }
-            }
+            });
         }
     };
 
     Ok(output.into())
 }
-
-struct ParsedInput {
-    ident: Ident,
-    arg_names: Vec<Ident>,
-    arg_types: Vec<PathSegment>,
-}
-
-fn parse_input(input: &FnArg) -> Result<ParsedInput, Error> {
-    let input_ident: Ident;
-    let mut arg_types = Vec::new();
-
-    match input {
-        FnArg::Receiver(_) => panic!("Input function cannot have self"),
-        FnArg::Typed(arg) => {
-            match arg.pat.as_ref() {
-                syn::Pat::Ident(ident) => {
-                    input_ident = ident.ident.clone();
-                }
-                _ => {
-                    return Err(Error::new_spanned(
-                        arg.pat.clone().into_token_stream(),
-                        "Expected an Identity".to_string(),
-                    ));
-                }
-            };
-            match arg.ty.as_ref() {
-                Type::Tuple(tuple) => {
-                    for tuple_elem in &tuple.elems {
-                        match tuple_elem {
-                            Type::Reference(reference) => {
-                                arg_types.push(match &reference.elem.as_ref() {
-                                    Type::Path(path) => path
-                                        .path
-                                        .segments
-                                        .last()
-                                        .unwrap_or_else(|| panic!("What? {:?}", path.path))
-                                        .clone(),
-                                    _ => {
-                                        return Err(Error::new_spanned(
-                                            reference.elem.clone().into_token_stream(),
-                                            "Expected a Component".to_string(),
-                                        ));
-                                    }
-                                })
-                            }
-                            _ => {
-                                return Err(Error::new_spanned(
-                                    tuple_elem.into_token_stream(),
-                                    "Expected the parameter type to be a reference"
-                                        .to_string(),
-                                ));
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    return Err(Error::new_spanned(
-                        arg.ty.clone().into_token_stream(),
-                        "Expected the parameter type to be a tuple".to_string(),
-                    ));
-                }
-            };
-        }
-    };
-    let arg_names: Vec<Ident> = arg_types
-        .iter()
-        .enumerate()
-        .map(|(idx, _)| format_ident!("p{idx}"))
-        .collect();
-
-    Ok(ParsedInput {
-        ident: input_ident,
-        arg_names,
-        arg_types,
-    })
-}
@@ -4,6 +4,7 @@ mod views;
 
 use bevy::prelude::Plugin;
 use bevy_egui::EguiPlugin;
+use bevy_utility_ai::inspector::AIInspectorPlugin;
 
 use self::state::GameViewState;
 use self::views::planet::PlanetViewPlugin;
@@ -16,6 +17,7 @@ impl Plugin for GamePlugin {
         app.add_plugin(EguiPlugin)
             .add_state::<GameViewState>()
             .add_plugin(SystemViewPlugin)
-            .add_plugin(PlanetViewPlugin);
+            .add_plugin(PlanetViewPlugin)
+            .add_plugin(AIInspectorPlugin);
     }
 }
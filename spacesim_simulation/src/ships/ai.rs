@@ -33,7 +33,8 @@ pub(super) fn define_ship_ai(app: &mut App) {
         .add_decision::<ActionMoveToPlanet>(vec![
             Consideration::targeted_filter::<IsPlanet>(),
             Consideration::targeted(system_distance)
-                .with_response_curve(LinearCurve::new(-1.0 / 75_000_000.0).shifted(0.0, 1.0))
+                .with_input_range(0.0, 75_000_000.0)
+                .with_response_curve(LinearCurve::new(-1.0).shifted(0.0, 1.0))
                 .set_input_name("distance_to_planet".into()),
         ])
         .register(app);